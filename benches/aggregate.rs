@@ -0,0 +1,54 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use toronto_covid::{aggregate, Aggregates, CovidEntry, Names};
+
+/// A synthetic dataset spread evenly over the known neighbourhoods.
+fn entries(n: usize, names: &Names) -> Vec<CovidEntry> {
+    let names = names.known();
+    (0..n)
+        .map(|i| {
+            let entry = serde_json::json!({
+                "_id": i,
+                "Outbreak Associated": if i % 3 == 0 { "Outbreak Associated" } else { "Sporadic" },
+                "Age Group": "50-59",
+                "Neighbourhood Name": names[i % names.len()],
+                "FSA": "M5V",
+                "Client Gender": if i % 2 == 0 { "FEMALE" } else { "MALE" },
+                "Outcome": "RESOLVED",
+                "Episode Date": "2020-04-01",
+            });
+            serde_json::from_value(entry).unwrap()
+        })
+        .collect()
+}
+
+fn aggregate_serial(covid: Vec<CovidEntry>, names: &Names) -> Aggregates {
+    let mut aggregates = Aggregates::default();
+    for e in covid.iter() {
+        aggregates.add(e, names);
+    }
+    aggregates
+}
+
+fn bench_aggregate(c: &mut Criterion) {
+    let names = Names::default();
+    let covid = entries(100_000, &names);
+    let mut group = c.benchmark_group("aggregate 100k");
+    group.bench_function("serial", |b| {
+        b.iter_batched(
+            || covid.clone(),
+            |covid| aggregate_serial(covid, &names),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("parallel", |b| {
+        b.iter_batched(
+            || covid.clone(),
+            |covid| aggregate(covid, &names),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_aggregate);
+criterion_main!(benches);
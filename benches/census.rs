@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde::Deserialize;
+use std::collections::HashMap;
+use toronto_covid::{age, parse_census, CensusEntryCategory, DEFAULT_POPULATION_FIELD};
+
+/// `CensusEntry` as it would be with a derived `Deserialize`, for comparison.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct FlattenedEntry {
+    #[serde(rename = "_id")]
+    id: u32,
+    #[serde(rename = "Characteristic")]
+    characteristic: String,
+    #[serde(rename = "Category")]
+    category: String,
+    #[serde(rename = "Topic")]
+    topic: String,
+    #[serde(rename = "Data Source")]
+    data_source: String,
+    #[serde(flatten)]
+    neighbourhoods: HashMap<String, Option<String>>,
+}
+
+fn bench_census(c: &mut Criterion) {
+    let data = std::fs::read_to_string("neighbourhood-profiles-2016-csv.json").unwrap();
+    let mut group = c.benchmark_group("census parse");
+    group.sample_size(20);
+    group.bench_function("every row, serde flatten", |b| {
+        b.iter(|| serde_json::from_str::<Vec<FlattenedEntry>>(&data).unwrap())
+    });
+    group.bench_function("every row", |b| {
+        b.iter(|| serde_json::from_str::<Vec<CensusEntryCategory>>(&data).unwrap())
+    });
+    group.bench_function("needed rows", |b| {
+        b.iter(|| {
+            parse_census(&data, DEFAULT_POPULATION_FIELD, |row| {
+                age::is_age_row(row.category, row.topic)
+            })
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_census);
+criterion_main!(benches);
@@ -0,0 +1,138 @@
+//! Direct age standardization of case rates.
+//!
+//! Each neighbourhood's age-specific rates are weighted by the share of the reference standard
+//! population in that age group. The standard used here is the City of Toronto's own 2016 census
+//! population, from the census's `CITY_OF_TORONTO` column, so a standardized rate reads as "the
+//! rate this neighbourhood would have if it had the city's age structure".
+
+use crate::{parse_census_number, AgeGroup, CensusEntryCategory, Names, POPULATION_CATEGORY};
+use std::collections::{BTreeMap, HashMap};
+
+pub const AGE_TOPIC: &str = "Age characteristics";
+
+/// Population in each known age group.
+pub type AgeBands = BTreeMap<AgeGroup, u32>;
+
+/// Every age group a population can be broken down into.
+const GROUPS: [AgeGroup; 9] = [
+    AgeGroup::LtEq19,
+    AgeGroup::R20to29,
+    AgeGroup::R30to39,
+    AgeGroup::R40to49,
+    AgeGroup::R50to59,
+    AgeGroup::R60to69,
+    AgeGroup::R70to79,
+    AgeGroup::R80to89,
+    AgeGroup::R90Plus,
+];
+
+/// The lower bound in years of a census age band, e.g. 5 for "Female: 05 to 09 years" or 100 for
+/// "Male: 100 years and over". Only the sex-specific five year bands are read, so that the
+/// overlapping summary rows like "Seniors (65+ years)" aren't counted twice.
+fn band_start(characteristic: &str) -> Option<u32> {
+    let characteristic = characteristic.trim();
+    let band = characteristic
+        .strip_prefix("Male: ")
+        .or_else(|| characteristic.strip_prefix("Female: "))?;
+    let (start, _) = band.split_once(' ')?;
+    start.parse().ok()
+}
+
+/// Whether a census row is one of the age breakdowns `age_bands` reads.
+pub fn is_age_row(category: &str, topic: &str) -> bool {
+    category == POPULATION_CATEGORY && topic == AGE_TOPIC
+}
+
+/// The population of each neighbourhood by age group, keyed by normalized name. Neighbourhoods
+/// missing a value for any band are left out entirely, since a partial breakdown can't be
+/// standardized.
+pub fn age_bands(census: &[CensusEntryCategory], names: &Names) -> HashMap<String, AgeBands> {
+    let mut bands: HashMap<String, Option<AgeBands>> = HashMap::new();
+    let rows = census
+        .iter()
+        .map(CensusEntryCategory::entry)
+        .filter(|e| is_age_row(&e.category, &e.topic));
+    for entry in rows {
+        let group = match band_start(&entry.characteristic) {
+            Some(start) => AgeGroup::from_age(start),
+            None => continue,
+        };
+        for (name, value) in entry.neighbourhoods.iter() {
+            let bands = bands
+                .entry(names.canonicalize(name).to_owned())
+                .or_insert_with(|| Some(AgeBands::new()));
+            match value.as_deref().and_then(parse_census_number) {
+                Some(value) => {
+                    if let Some(bands) = bands {
+                        *bands.entry(group).or_insert(0) += value as u32;
+                    }
+                }
+                None => *bands = None,
+            }
+        }
+    }
+    bands
+        .into_iter()
+        .filter_map(|(name, bands)| {
+            let bands = bands?;
+            if GROUPS.iter().all(|g| bands.contains_key(g)) {
+                Some((name, bands))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Directly age-standardized cases per 100,000 residents:
+///
+/// ```text
+/// sum over age groups g of (cases[g] / population[g]) * (standard[g] / sum of standard) * 100,000
+/// ```
+///
+/// Cases of unknown age are left out. Returns `None` when the rate can't be computed, i.e. the
+/// standard population is empty, or an age group has cases but no population to divide them by.
+pub fn standardized_rate(
+    cases: &BTreeMap<AgeGroup, u32>,
+    population: &AgeBands,
+    standard: &AgeBands,
+) -> Option<f64> {
+    let standard_total: u32 = GROUPS.iter().filter_map(|g| standard.get(g)).sum();
+    if standard_total == 0 {
+        return None;
+    }
+    let mut rate = 0.0;
+    for group in GROUPS.iter() {
+        let cases = cases.get(group).copied().unwrap_or(0);
+        let population = population.get(group).copied().unwrap_or(0);
+        let weight = standard.get(group).copied().unwrap_or(0) as f64 / standard_total as f64;
+        if population == 0 {
+            if cases > 0 {
+                return None;
+            }
+            continue;
+        }
+        rate += cases as f64 / population as f64 * weight;
+    }
+    Some(rate * 100_000.0)
+}
+
+/// The mean age of cases, taking each case to be at its age group's `midpoint`. Cases of unknown
+/// age are left out, and `None` is returned when there are no others.
+pub fn mean_age(cases: &BTreeMap<AgeGroup, u32>) -> Option<f64> {
+    let (count, years) = cases
+        .iter()
+        .filter_map(|(group, &n)| Some((n, group.midpoint()? * n as f64)))
+        .fold((0, 0.0), |(count, years), (n, y)| (count + n, years + y));
+    if count == 0 {
+        None
+    } else {
+        Some(years / count as f64)
+    }
+}
+
+/// The number of cases without a known age group.
+pub fn unknown_age_count(total: u32, cases: &BTreeMap<AgeGroup, u32>) -> u32 {
+    let known: u32 = GROUPS.iter().filter_map(|g| cases.get(g)).sum();
+    total - known
+}
@@ -0,0 +1,42 @@
+use quicli::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use toronto_covid::CensusData;
+
+const CENSUS: &str = ".cache/census.bin";
+
+#[derive(Serialize, Deserialize)]
+struct CachedCensus {
+    /// The census file the data was read from, and its modification time at the time.
+    source: PathBuf,
+    modified: SystemTime,
+    data: CensusData,
+}
+
+fn modified(path: &Path) -> std::io::Result<SystemTime> {
+    std::fs::metadata(path)?.modified()
+}
+
+/// The data previously extracted from `census`, if it hasn't been modified since.
+pub fn load_census(census: &Path) -> Option<CensusData> {
+    let file = std::io::BufReader::new(std::fs::File::open(CENSUS).ok()?);
+    let cached: CachedCensus = bincode::deserialize_from(file).ok()?;
+    if cached.source == census && cached.modified == modified(census).ok()? {
+        Some(cached.data)
+    } else {
+        None
+    }
+}
+
+pub fn store_census(census: &Path, data: &CensusData) -> Result<(), Error> {
+    let cached = CachedCensus {
+        source: census.to_owned(),
+        modified: modified(census)?,
+        data: data.clone(),
+    };
+    std::fs::create_dir_all(".cache")?;
+    let file = std::io::BufWriter::new(std::fs::File::create(CENSUS)?);
+    bincode::serialize_into(file, &cached)?;
+    Ok(())
+}
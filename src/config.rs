@@ -0,0 +1,111 @@
+//! Defaults for the command line options read from a TOML file, so a team can share one set of
+//! paths and settings. Options given on the command line win over the file.
+
+use std::convert::TryFrom;
+use std::ffi::OsString;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error("{0}: expected a string, number, boolean or array of them")]
+    Unsupported(String),
+    #[error("{0}: expected true, false or how many times to repeat it")]
+    Count(String),
+    #[error("a config file can't set --config")]
+    Nested,
+}
+
+/// The command line arguments one config value stands for: `--key value` for a string or number,
+/// `--key` alone for `true`, nothing for `false`, and `--key value` once per element of an array.
+/// A `counted` option, such as `--verbose`, takes no value, so a number `n` stands for `--key`
+/// given `n` times.
+fn option_args(
+    key: &str,
+    value: &toml::Value,
+    counted: bool,
+) -> Result<Vec<OsString>, ConfigError> {
+    let flag = OsString::from(format!("--{}", key));
+    if counted {
+        return match value {
+            toml::Value::Boolean(given) => Ok(vec![flag; *given as usize]),
+            toml::Value::Integer(n) => match usize::try_from(*n) {
+                Ok(n) => Ok(vec![flag; n]),
+                Err(_) => Err(ConfigError::Count(key.to_owned())),
+            },
+            _ => Err(ConfigError::Count(key.to_owned())),
+        };
+    }
+    let scalar = |value: &toml::Value| match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(n) => Some(n.to_string()),
+        toml::Value::Float(n) => Some(n.to_string()),
+        toml::Value::Datetime(d) => Some(d.to_string()),
+        _ => None,
+    };
+    match value {
+        toml::Value::Boolean(true) => Ok(vec![flag]),
+        toml::Value::Boolean(false) => Ok(Vec::new()),
+        toml::Value::Array(values) => {
+            let mut args = Vec::new();
+            for value in values.iter() {
+                let value =
+                    scalar(value).ok_or_else(|| ConfigError::Unsupported(key.to_owned()))?;
+                args.extend(vec![flag.clone(), value.into()]);
+            }
+            Ok(args)
+        }
+        value => match scalar(value) {
+            Some(value) => Ok(vec![flag, value.into()]),
+            None => Err(ConfigError::Unsupported(key.to_owned())),
+        },
+    }
+}
+
+/// Whether the command line gives the option with the long name `key`, either as `--key`,
+/// `--key=value` or, for options in `shorts`, by its short name.
+fn given(cli: &[OsString], key: &str, shorts: &[(char, &str)]) -> bool {
+    let long = format!("--{}", key);
+    let short = shorts
+        .iter()
+        .find(|(_, long)| *long == key)
+        .map(|(s, _)| *s);
+    cli.iter().filter_map(|arg| arg.to_str()).any(|arg| {
+        if let Some(rest) = arg.strip_prefix(long.as_str()) {
+            rest.is_empty() || rest.starts_with('=')
+        } else if arg.starts_with("--") {
+            false
+        } else {
+            let letters = arg.strip_prefix('-').unwrap_or_default();
+            short.is_some_and(|short| letters.starts_with(short))
+        }
+    })
+}
+
+/// Puts the options a config file sets in front of the command line `cli`, which starts with the
+/// program name, leaving out any the command line gives itself. Keys are the options' long names,
+/// with either dashes or underscores, e.g. `census_field = "Land area in square kilometres"`.
+/// `shorts` pairs each short option with its long name, and `counted` names the options counted by
+/// how often they're given, e.g. `verbose = 2` for `-vv`.
+pub fn merge(
+    config: &str,
+    cli: Vec<OsString>,
+    shorts: &[(char, &str)],
+    counted: &[&str],
+) -> Result<Vec<OsString>, ConfigError> {
+    let table = config.parse::<toml::Table>()?;
+    let mut cli = cli.into_iter();
+    let mut args = cli.next().into_iter().collect::<Vec<_>>();
+    let cli = cli.collect::<Vec<_>>();
+    for (key, value) in table.iter() {
+        let key = key.replace('_', "-");
+        if key == "config" {
+            return Err(ConfigError::Nested);
+        }
+        if !given(&cli, &key, shorts) {
+            args.extend(option_args(&key, value, counted.contains(&key.as_str()))?);
+        }
+    }
+    args.extend(cli);
+    Ok(args)
+}
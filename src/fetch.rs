@@ -0,0 +1,205 @@
+//! Downloading the input datasets from Toronto Open Data, retrying requests that fail on the
+//! server's side or never get a response.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::time::Duration;
+
+/// A single resource within a Toronto Open Data (CKAN) package.
+pub struct Dataset {
+    pub package: &'static str,
+    pub resource: &'static str,
+}
+
+pub const NEIGHBOURHOODS: Dataset = Dataset {
+    package: "neighbourhoods",
+    resource: "Neighbourhoods",
+};
+
+pub const COVID_CASES: Dataset = Dataset {
+    package: "covid-19-cases-in-toronto",
+    resource: "COVID19 cases",
+};
+
+pub const NEIGHBOURHOOD_PROFILES: Dataset = Dataset {
+    package: "neighbourhood-profiles",
+    resource: "neighbourhood-profiles-2016-csv",
+};
+
+/// How hard to try each request.
+#[derive(Debug, Clone)]
+pub struct Retry {
+    /// Tries per request, including the first.
+    pub attempts: u32,
+    /// The wait after the first failed try, doubled after each one after that.
+    pub backoff: Duration,
+    /// How long a single try may take, from connecting to the end of the body.
+    pub timeout: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry {
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("{url} failed after {attempts} attempt(s): {source}")]
+    Http {
+        url: String,
+        attempts: u32,
+        source: reqwest::Error,
+    },
+    #[error("couldn't set up an HTTP client: {0}")]
+    Client(reqwest::Error),
+    #[error("CKAN rejected the request for {0}")]
+    Rejected(String),
+    #[error("package {package} has no resource named {resource}")]
+    NoResource {
+        package: &'static str,
+        resource: &'static str,
+    },
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Deserialize)]
+struct PackageShow {
+    success: bool,
+    result: Package,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    resources: Vec<Resource>,
+}
+
+#[derive(Deserialize)]
+struct Resource {
+    id: String,
+    name: String,
+    url: String,
+    #[serde(default)]
+    datastore_active: bool,
+}
+
+/// A datastore dump in JSON, which holds the table's rows under `records` rather than being an
+/// array of them like the files the rest of the crate reads.
+#[derive(Deserialize)]
+struct DatastoreDump {
+    fields: Vec<Field>,
+    records: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    id: String,
+}
+
+impl DatastoreDump {
+    /// The rows as an array of objects keyed by column. The dump writes each row as an array of
+    /// values in the order of `fields`, and those are zipped up with the field ids.
+    fn into_rows(self) -> Vec<Value> {
+        let DatastoreDump { fields, records } = self;
+        records
+            .into_iter()
+            .map(|record| match record {
+                Value::Array(values) => {
+                    let ids = fields.iter().map(|f| f.id.clone());
+                    Value::Object(ids.zip(values).collect::<Map<_, _>>())
+                }
+                record => record,
+            })
+            .collect()
+    }
+}
+
+/// The body of `url`, trying again after timeouts, connection failures and 5xx responses. Other
+/// error responses won't go away on their own, so they fail straight away.
+fn get(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    retry: &Retry,
+) -> Result<Vec<u8>, FetchError> {
+    let attempts = retry.attempts.max(1);
+    let mut backoff = retry.backoff;
+    for attempt in 1.. {
+        log::info!("fetching {} (attempt {}/{})", url, attempt, attempts);
+        let body = client
+            .get(url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes());
+        let e = match body {
+            Ok(body) => return Ok(body.to_vec()),
+            Err(e) => e,
+        };
+        let permanent = e.status().is_some_and(|status| !status.is_server_error());
+        if permanent || attempt >= attempts {
+            return Err(FetchError::Http {
+                url: url.to_owned(),
+                attempts: attempt,
+                source: e,
+            });
+        }
+        log::warn!("fetching {} failed, retrying in {:?}: {}", url, backoff, e);
+        std::thread::sleep(backoff);
+        backoff *= 2;
+    }
+    unreachable!("the attempts are unbounded")
+}
+
+/// Downloads the latest copy of `dataset` to `dest`, replacing whatever is there. `dest` is only
+/// touched once the download has succeeded.
+pub fn fetch(
+    base_url: &str,
+    dataset: &Dataset,
+    dest: &Path,
+    retry: &Retry,
+) -> Result<(), FetchError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(retry.timeout)
+        .build()
+        .map_err(FetchError::Client)?;
+    let base_url = base_url.trim_end_matches('/');
+    let package_url = format!(
+        "{}/api/3/action/package_show?id={}",
+        base_url, dataset.package
+    );
+    let package: PackageShow = serde_json::from_slice(&get(&client, &package_url, retry)?)?;
+    if !package.success {
+        return Err(FetchError::Rejected(package_url));
+    }
+
+    let resource = package
+        .result
+        .resources
+        .into_iter()
+        .find(|r| r.name == dataset.resource)
+        .ok_or(FetchError::NoResource {
+            package: dataset.package,
+            resource: dataset.resource,
+        })?;
+    // tabular resources are only available as JSON through a datastore dump
+    let url = if resource.datastore_active {
+        format!("{}/datastore/dump/{}?format=json", base_url, resource.id)
+    } else {
+        resource.url.clone()
+    };
+
+    let mut body = get(&client, &url, retry)?;
+    if resource.datastore_active {
+        let dump: DatastoreDump = serde_json::from_slice(&body)?;
+        body = serde_json::to_vec(&dump.into_rows())?;
+    }
+    crate::output::replace_atomic(dest, |tmp| std::fs::write(tmp, &body))?;
+    Ok(())
+}
@@ -0,0 +1,293 @@
+//! A small expression language for selecting COVID rows, e.g.
+//! `age_group>=60 && outbreak_associated==Sporadic`.
+//!
+//! An expression is one or more comparisons joined by `&&`, and any number of those joined by
+//! `||`, with `&&` binding tighter. Each comparison is `field op value`, where `op` is one of `==`,
+//! `!=`, `<`, `<=`, `>` and `>=`. Only `age_group` and `episode_date` can be ordered; the other
+//! fields support equality only. Values may be quoted with `"` or `'`, and are matched
+//! case-insensitively.
+
+use crate::{source_category, AgeGroup, Classification, CovidEntry, OutbreakAssociated, Outcome};
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+
+const FIELDS: &str = "age_group, outbreak_associated, classification, outcome, gender, \
+                      neighbourhood, fsa, source_of_infection, ever_hospitalized, ever_icu, \
+                      ever_intubated, episode_date";
+
+#[derive(Debug, thiserror::Error)]
+pub enum FilterError {
+    #[error("expected a comparison like `field==value`, got {0:?}")]
+    Syntax(String),
+    #[error("unknown field {0:?}, expected one of {FIELDS}")]
+    UnknownField(String),
+    #[error("`{op}` can't be used with {field}, which only supports == and !=")]
+    Unordered { field: String, op: &'static str },
+    #[error("invalid value {value:?} for {field}")]
+    InvalidValue { field: String, value: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    /// Every operator by its symbol. Where one symbol starts another, the longer one wins.
+    const ALL: [(&'static str, Op); 6] = [
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ];
+
+    fn symbol(self) -> &'static str {
+        Op::ALL.iter().find(|&&(_, op)| op == self).unwrap().0
+    }
+
+    fn is_ordered(self) -> bool {
+        !matches!(self, Op::Eq | Op::Ne)
+    }
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    AgeGroup(AgeGroup),
+    Date(NaiveDate),
+    Outbreak(OutbreakAssociated),
+    Classification(Classification),
+    Outcome(Outcome),
+    Bool(bool),
+    /// Free text, lowercased.
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    AgeGroup,
+    OutbreakAssociated,
+    Classification,
+    Outcome,
+    Gender,
+    Neighbourhood,
+    Fsa,
+    Source,
+    EverHospitalized,
+    EverIcu,
+    EverIntubated,
+    EpisodeDate,
+}
+
+/// The variant whose label or name matches `value`, ignoring case.
+fn variant<T>(value: &str, variants: &[(&[&str], T)]) -> Option<T>
+where
+    T: Clone,
+{
+    variants
+        .iter()
+        .find(|(names, _)| names.iter().any(|n| n.eq_ignore_ascii_case(value)))
+        .map(|(_, v)| v.clone())
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "age_group" => Field::AgeGroup,
+            "outbreak_associated" => Field::OutbreakAssociated,
+            "classification" => Field::Classification,
+            "outcome" => Field::Outcome,
+            "gender" => Field::Gender,
+            "neighbourhood" => Field::Neighbourhood,
+            "fsa" => Field::Fsa,
+            "source_of_infection" => Field::Source,
+            "ever_hospitalized" => Field::EverHospitalized,
+            "ever_icu" => Field::EverIcu,
+            "ever_intubated" => Field::EverIntubated,
+            "episode_date" => Field::EpisodeDate,
+            _ => return None,
+        })
+    }
+
+    fn is_ordered(self) -> bool {
+        matches!(self, Field::AgeGroup | Field::EpisodeDate)
+    }
+
+    /// Reads a value for this field. A number given for `age_group` stands for the group containing
+    /// that age, so `age_group>=60` selects 60-69 and older.
+    fn parse(self, value: &str) -> Option<Value> {
+        Some(match self {
+            Field::AgeGroup => {
+                let group = match value.parse() {
+                    Ok(years) => AgeGroup::from_age(years),
+                    Err(_) => AgeGroup::from_label(value),
+                };
+                if group == AgeGroup::Unknown {
+                    return None;
+                }
+                Value::AgeGroup(group)
+            }
+            Field::EpisodeDate => Value::Date(value.parse().ok()?),
+            Field::OutbreakAssociated => Value::Outbreak(variant(
+                value,
+                &[
+                    (
+                        &["Outbreak Associated", "Outbreak"],
+                        OutbreakAssociated::Outbreak,
+                    ),
+                    (&["Sporadic"], OutbreakAssociated::Sporadic),
+                ],
+            )?),
+            Field::Classification => Value::Classification(variant(
+                value,
+                &[
+                    (&["CONFIRMED"], Classification::Confirmed),
+                    (&["PROBABLE"], Classification::Probable),
+                ],
+            )?),
+            Field::Outcome => Value::Outcome(variant(
+                value,
+                &[
+                    (&["ACTIVE"], Outcome::Active),
+                    (&["RESOLVED"], Outcome::Resolved),
+                    (&["FATAL"], Outcome::Fatal),
+                ],
+            )?),
+            Field::EverHospitalized | Field::EverIcu | Field::EverIntubated => {
+                Value::Bool(variant(
+                    value,
+                    &[(&["yes", "true"], true), (&["no", "false"], false)],
+                )?)
+            }
+            Field::Source => Value::Text(source_category(Some(value)).to_lowercase()),
+            Field::Gender | Field::Neighbourhood | Field::Fsa => Value::Text(value.to_lowercase()),
+        })
+    }
+
+    /// This field of an entry, or `None` if it's blank or unknown.
+    fn get(self, entry: &CovidEntry) -> Option<Value> {
+        let text = |s: &Option<String>| s.as_deref().map(|s| Value::Text(s.trim().to_lowercase()));
+        match self {
+            Field::AgeGroup => match entry.age_group {
+                Some(AgeGroup::Unknown) | None => None,
+                Some(group) => Some(Value::AgeGroup(group)),
+            },
+            Field::EpisodeDate => entry.episode_date.map(Value::Date),
+            Field::OutbreakAssociated => Some(Value::Outbreak(entry.outbreak_associated.clone())),
+            Field::Classification => entry.classification.clone().map(Value::Classification),
+            Field::Outcome => entry.outcome.clone().map(Value::Outcome),
+            Field::EverHospitalized => entry.ever_hospitalized.map(Value::Bool),
+            Field::EverIcu => entry.ever_icu.map(Value::Bool),
+            Field::EverIntubated => entry.ever_intubated.map(Value::Bool),
+            Field::Source => Some(Value::Text(
+                source_category(entry.source_of_infection.as_deref()).to_lowercase(),
+            )),
+            Field::Gender => text(&entry.gender),
+            Field::Neighbourhood => text(&entry.neighbourhood),
+            Field::Fsa => text(&entry.fsa),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Comparison {
+    field: Field,
+    op: Op,
+    value: Value,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Result<Self, FilterError> {
+        let (at, symbol, op) = Op::ALL
+            .iter()
+            .filter_map(|&(symbol, op)| s.find(symbol).map(|at| (at, symbol, op)))
+            .min_by_key(|&(at, symbol, _)| (at, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(|| FilterError::Syntax(s.to_owned()))?;
+        let name = s[..at].trim();
+        let value = s[at + symbol.len()..].trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        if name.is_empty() || value.is_empty() {
+            return Err(FilterError::Syntax(s.to_owned()));
+        }
+
+        let field =
+            Field::from_name(name).ok_or_else(|| FilterError::UnknownField(name.to_owned()))?;
+        if op.is_ordered() && !field.is_ordered() {
+            return Err(FilterError::Unordered {
+                field: name.to_owned(),
+                op: op.symbol(),
+            });
+        }
+        let value = field
+            .parse(value)
+            .ok_or_else(|| FilterError::InvalidValue {
+                field: name.to_owned(),
+                value: value.to_owned(),
+            })?;
+        Ok(Comparison { field, op, value })
+    }
+
+    /// Blank and unknown values only satisfy `!=`.
+    fn matches(&self, entry: &CovidEntry) -> bool {
+        let actual = match self.field.get(entry) {
+            Some(actual) => actual,
+            None => return self.op == Op::Ne,
+        };
+        let ordering = match (&actual, &self.value) {
+            (Value::AgeGroup(a), Value::AgeGroup(b)) => a.cmp(b),
+            (Value::Date(a), Value::Date(b)) => a.cmp(b),
+            (a, b) if a == b => Ordering::Equal,
+            // unequal values of an unordered field; only the != operator can get here
+            _ => Ordering::Less,
+        };
+        self.op.holds(ordering)
+    }
+}
+
+/// A parsed `--where` expression.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    /// Alternatives joined by `||`, each a conjunction of comparisons joined by `&&`.
+    any: Vec<Vec<Comparison>>,
+}
+
+impl Filter {
+    pub fn matches(&self, entry: &CovidEntry) -> bool {
+        self.any
+            .iter()
+            .any(|all| all.iter().all(|comparison| comparison.matches(entry)))
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let any = s
+            .split("||")
+            .map(|all| all.split("&&").map(Comparison::parse).collect())
+            .collect::<Result<_, _>>()?;
+        Ok(Filter { any })
+    }
+}
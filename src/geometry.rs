@@ -0,0 +1,273 @@
+use geo::algorithm::area::Area;
+use geo::algorithm::centroid::Centroid;
+use geo::algorithm::chamberlain_duquette_area::ChamberlainDuquetteArea;
+use geo::algorithm::contains::Contains;
+use std::convert::TryInto;
+
+/// The parts of a polygon or multipolygon. Other geometry types have none, and neither does a
+/// polygon with a position missing a coordinate, which the conversion would panic on.
+pub fn polygons(value: &geojson::Value) -> Option<Vec<geo::Polygon<f64>>> {
+    let rings = match value {
+        geojson::Value::Polygon(rings) => rings.iter().collect(),
+        geojson::Value::MultiPolygon(polygons) => polygons.iter().flatten().collect(),
+        _ => Vec::new(),
+    };
+    if rings
+        .iter()
+        .flat_map(|ring| ring.iter())
+        .any(|p| p.len() < 2)
+    {
+        return None;
+    }
+    match value {
+        geojson::Value::Polygon(_) => {
+            let polygon: geo::Polygon<f64> = value.clone().try_into().ok()?;
+            Some(vec![polygon])
+        }
+        geojson::Value::MultiPolygon(_) => {
+            let polygons: geo::MultiPolygon<f64> = value.clone().try_into().ok()?;
+            Some(polygons.0)
+        }
+        _ => None,
+    }
+}
+
+/// Area of a (multi)polygon in square kilometres, treating coordinates as WGS84 longitude and
+/// latitude. Other geometry types have no area.
+pub fn area_km2(value: &geojson::Value) -> Option<f64> {
+    let area_m2: f64 = polygons(value)?
+        .iter()
+        .map(|p| p.chamberlain_duquette_unsigned_area())
+        .sum();
+    Some(area_m2 / 1_000_000.0)
+}
+
+/// One polygon covering every part, their convex hull. It dissolves the borders between adjoining
+/// parts, at the cost of also covering any gaps and bays between them. `None` without any parts.
+pub fn dissolve(parts: Vec<geo::Polygon<f64>>) -> Option<geo::Polygon<f64>> {
+    use geo::algorithm::convex_hull::ConvexHull;
+    if parts.is_empty() {
+        return None;
+    }
+    Some(geo::MultiPolygon(parts).convex_hull())
+}
+
+/// Centroid of a polygon, or of the largest part of a multipolygon, as `(x, y)`.
+pub fn centroid(value: &geojson::Value) -> Option<(f64, f64)> {
+    let largest = polygons(value)?
+        .into_iter()
+        .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()))?;
+    largest.centroid().map(|p| (p.x(), p.y()))
+}
+
+/// The bounding box of a (multi)polygon, as `[min_x, min_y, max_x, max_y]`, covering every ring of
+/// every part. Positions missing a coordinate are skipped. Other geometry types have none.
+pub fn bbox(value: &geojson::Value) -> Option<[f64; 4]> {
+    let rings = match value {
+        geojson::Value::Polygon(rings) => rings.iter().collect(),
+        geojson::Value::MultiPolygon(polygons) => polygons.iter().flatten().collect(),
+        _ => Vec::new(),
+    };
+    rings
+        .into_iter()
+        .flatten()
+        .filter(|p| p.len() >= 2)
+        .fold(None, |bbox, p| {
+            let [min_x, min_y, max_x, max_y] = bbox.unwrap_or([p[0], p[1], p[0], p[1]]);
+            Some([
+                min_x.min(p[0]),
+                min_y.min(p[1]),
+                max_x.max(p[0]),
+                max_y.max(p[1]),
+            ])
+        })
+}
+
+/// Whether a (multi)polygon contains the point `(x, y)`. Other geometry types contain nothing.
+pub fn contains(value: &geojson::Value, x: f64, y: f64) -> bool {
+    let point = geo::Point::new(x, y);
+    polygons(value).is_some_and(|parts| parts.iter().any(|p| p.contains(&point)))
+}
+
+/// The first feature whose geometry contains the point `(x, y)`.
+pub fn locate(
+    neighbourhoods: &geojson::FeatureCollection,
+    x: f64,
+    y: f64,
+) -> Option<&geojson::Feature> {
+    neighbourhoods.features.iter().find(|feature| {
+        feature
+            .geometry
+            .as_ref()
+            .is_some_and(|g| contains(&g.value, x, y))
+    })
+}
+
+/// The fewest positions a simplified ring may keep, a closed triangle. Rings that would end up
+/// with fewer are left as they were.
+const MIN_RING_POSITIONS: usize = 4;
+
+/// A (multi)polygon with each ring simplified by Douglas-Peucker with the given tolerance, in
+/// degrees. Other geometry types, and polygons that can't be converted, give `None`.
+pub fn simplify(value: &geojson::Value, tolerance: f64) -> Option<geojson::Value> {
+    use geo::algorithm::simplify::Simplify;
+    let ring = |ring: &geo::LineString<f64>| {
+        let simplified = ring.simplify(&tolerance);
+        if simplified.0.len() < MIN_RING_POSITIONS {
+            ring.clone()
+        } else {
+            simplified
+        }
+    };
+    let polygons = polygons(value)?
+        .iter()
+        .map(|p| geo::Polygon::new(ring(p.exterior()), p.interiors().iter().map(ring).collect()))
+        .collect::<Vec<_>>();
+    match value {
+        geojson::Value::Polygon(_) => Some(geojson::Value::from(&polygons[0])),
+        _ => Some(geojson::Value::from(&geo::MultiPolygon(polygons))),
+    }
+}
+
+/// The radius of the sphere EPSG:3857 treats the earth as, in metres.
+const EARTH_RADIUS_M: f64 = 6_378_137.0;
+/// The latitude at which spherical Mercator makes the world square. Positions further north or
+/// south are clamped to it, as the projection sends the poles to infinity.
+const MAX_MERCATOR_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// A WGS84 longitude and latitude projected to spherical ("Web") Mercator, EPSG:3857, as `(x, y)`
+/// in metres.
+pub fn web_mercator(lng: f64, lat: f64) -> (f64, f64) {
+    let lat = lat.clamp(-MAX_MERCATOR_LATITUDE, MAX_MERCATOR_LATITUDE);
+    let x = EARTH_RADIUS_M * lng.to_radians();
+    let y = EARTH_RADIUS_M
+        * (std::f64::consts::FRAC_PI_4 + lat.to_radians() / 2.0)
+            .tan()
+            .ln();
+    (x, y)
+}
+
+/// Projects every position of a geometry, of any type, with `web_mercator`. Positions missing a
+/// coordinate are left as they are.
+pub fn to_web_mercator(value: &mut geojson::Value) {
+    fn project(position: &mut [f64]) {
+        if position.len() >= 2 {
+            let (x, y) = web_mercator(position[0], position[1]);
+            position[0] = x;
+            position[1] = y;
+        }
+    }
+    match value {
+        geojson::Value::Point(p) => project(p),
+        geojson::Value::MultiPoint(ps) | geojson::Value::LineString(ps) => {
+            ps.iter_mut().for_each(|p| project(p))
+        }
+        geojson::Value::MultiLineString(ls) | geojson::Value::Polygon(ls) => {
+            ls.iter_mut().flatten().for_each(|p| project(p))
+        }
+        geojson::Value::MultiPolygon(polygons) => polygons
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(|p| project(p)),
+        geojson::Value::GeometryCollection(geometries) => geometries
+            .iter_mut()
+            .for_each(|g| to_web_mercator(&mut g.value)),
+    }
+}
+
+/// Something wrong with a polygon that would throw off its area or centroid.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Problem {
+    #[error("not a polygon or multipolygon")]
+    NotAPolygon,
+    #[error("ring {ring} of part {part} isn't closed")]
+    Unclosed { part: usize, ring: usize },
+    #[error("ring {ring} of part {part} has fewer than 4 positions")]
+    TooShort { part: usize, ring: usize },
+    #[error("ring {ring} of part {part} has a position without both coordinates")]
+    MissingCoordinate { part: usize, ring: usize },
+    #[error("ring {ring} of part {part} has a non-finite coordinate")]
+    NonFinite { part: usize, ring: usize },
+    #[error("ring {ring} of part {part} intersects itself")]
+    SelfIntersecting { part: usize, ring: usize },
+    #[error("part {part} has no area")]
+    Empty { part: usize },
+}
+
+/// Whether two segments of a ring cross or touch somewhere other than an endpoint they share as
+/// neighbours.
+fn segments_meet(ring: &[geo::Coordinate<f64>], i: usize, j: usize) -> bool {
+    use geo::algorithm::intersects::Intersects;
+    let n = ring.len() - 1;
+    let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+    let a = geo::Line::new(ring[i], ring[i + 1]);
+    let b = geo::Line::new(ring[j], ring[j + 1]);
+    if !adjacent {
+        return a.intersects(&b);
+    }
+    // neighbours always share an endpoint, so only overlapping (doubling back) counts
+    let (shared, other) = if j == i + 1 {
+        (ring[j], ring[i])
+    } else {
+        (ring[i], ring[i + 1])
+    };
+    let far = if j == i + 1 { ring[j + 1] } else { ring[j] };
+    let cross =
+        (other.x - shared.x) * (far.y - shared.y) - (other.y - shared.y) * (far.x - shared.x);
+    let dot = (other.x - shared.x) * (far.x - shared.x) + (other.y - shared.y) * (far.y - shared.y);
+    cross == 0.0 && dot > 0.0
+}
+
+/// Whether a closed ring crosses or touches itself. Repeated positions are ignored.
+fn is_self_intersecting(ring: &[geo::Coordinate<f64>]) -> bool {
+    let mut ring = ring.to_vec();
+    ring.dedup();
+    if ring.len() < 4 {
+        return false;
+    }
+    let ring = ring.as_slice();
+    let segments = ring.len() - 1;
+    (0..segments).any(|i| (i + 1..segments).any(|j| segments_meet(ring, i, j)))
+}
+
+/// Checks that a (multi)polygon's rings are closed, long enough, finite and don't intersect
+/// themselves, and that each part has some area. An empty list means no problems were found.
+pub fn problems(value: &geojson::Value) -> Vec<Problem> {
+    let parts = match value {
+        geojson::Value::Polygon(rings) => vec![rings],
+        geojson::Value::MultiPolygon(polygons) => polygons.iter().collect(),
+        _ => return vec![Problem::NotAPolygon],
+    };
+    let mut problems = Vec::new();
+    for (part, rings) in parts.into_iter().enumerate() {
+        let mut line_strings = Vec::new();
+        for (ring, positions) in rings.iter().enumerate() {
+            let coords = positions
+                .iter()
+                .filter(|p| p.len() >= 2)
+                .map(|p| geo::Coordinate { x: p[0], y: p[1] })
+                .collect::<Vec<_>>();
+            if coords.len() < positions.len() {
+                problems.push(Problem::MissingCoordinate { part, ring });
+            } else if coords.iter().any(|c| !c.x.is_finite() || !c.y.is_finite()) {
+                problems.push(Problem::NonFinite { part, ring });
+            } else if coords.len() < 4 {
+                problems.push(Problem::TooShort { part, ring });
+            } else if coords.first() != coords.last() {
+                problems.push(Problem::Unclosed { part, ring });
+            } else if is_self_intersecting(&coords) {
+                problems.push(Problem::SelfIntersecting { part, ring });
+            }
+            line_strings.push(geo::LineString(coords));
+        }
+        let mut line_strings = line_strings.into_iter();
+        let area = line_strings.next().map_or(0.0, |exterior| {
+            geo::Polygon::new(exterior, line_strings.collect()).unsigned_area()
+        });
+        if area == 0.0 {
+            problems.push(Problem::Empty { part });
+        }
+    }
+    problems
+}
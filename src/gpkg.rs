@@ -0,0 +1,183 @@
+//! GeoPackage export, for GIS tools like QGIS. The neighbourhoods are written as a single
+//! `neighbourhoods` feature table of WGS84 multipolygons.
+
+use crate::geometry;
+use geojson::FeatureCollection;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::path::Path;
+
+const TABLE: &str = "neighbourhoods";
+const SRS_ID: i32 = 4326;
+const WGS84: &str =
+    "GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,\
+AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,\
+AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],\
+AUTHORITY[\"EPSG\",\"4326\"]]";
+
+/// The tables every GeoPackage must have, with the spatial reference systems the standard
+/// requires plus WGS84.
+const SCHEMA: &str = "
+CREATE TABLE gpkg_spatial_ref_sys (
+    srs_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL PRIMARY KEY,
+    organization TEXT NOT NULL,
+    organization_coordsys_id INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    description TEXT
+);
+CREATE TABLE gpkg_contents (
+    table_name TEXT NOT NULL PRIMARY KEY,
+    data_type TEXT NOT NULL,
+    identifier TEXT UNIQUE,
+    description TEXT DEFAULT '',
+    last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+    min_x DOUBLE,
+    min_y DOUBLE,
+    max_x DOUBLE,
+    max_y DOUBLE,
+    srs_id INTEGER REFERENCES gpkg_spatial_ref_sys(srs_id)
+);
+CREATE TABLE gpkg_geometry_columns (
+    table_name TEXT NOT NULL REFERENCES gpkg_contents(table_name),
+    column_name TEXT NOT NULL,
+    geometry_type_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL REFERENCES gpkg_spatial_ref_sys(srs_id),
+    z TINYINT NOT NULL,
+    m TINYINT NOT NULL,
+    PRIMARY KEY (table_name, column_name)
+);
+INSERT INTO gpkg_spatial_ref_sys VALUES
+    ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', NULL),
+    ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', NULL);
+";
+
+/// A bounding box as `[min_x, max_x, min_y, max_y]`, the order GeoPackage envelopes use.
+type Envelope = [f64; 4];
+
+fn extend(envelope: &mut Option<Envelope>, x: f64, y: f64) {
+    let e = envelope.get_or_insert([x, x, y, y]);
+    e[0] = e[0].min(x);
+    e[1] = e[1].max(x);
+    e[2] = e[2].min(y);
+    e[3] = e[3].max(y);
+}
+
+/// A (multi)polygon as a GeoPackage geometry blob: the standard header with an XY envelope,
+/// followed by little-endian WKB for a multipolygon.
+fn geometry_blob(value: &geojson::Value) -> Option<(Vec<u8>, Envelope)> {
+    let polygons = geometry::polygons(value)?;
+    let mut wkb = Vec::new();
+    let mut envelope = None;
+    wkb.push(1);
+    wkb.extend(&6u32.to_le_bytes());
+    wkb.extend(&(polygons.len() as u32).to_le_bytes());
+    for polygon in polygons.iter() {
+        wkb.push(1);
+        wkb.extend(&3u32.to_le_bytes());
+        let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+        wkb.extend(&(1 + polygon.interiors().len() as u32).to_le_bytes());
+        for ring in rings {
+            wkb.extend(&(ring.0.len() as u32).to_le_bytes());
+            for c in ring.0.iter() {
+                extend(&mut envelope, c.x, c.y);
+                wkb.extend(&c.x.to_le_bytes());
+                wkb.extend(&c.y.to_le_bytes());
+            }
+        }
+    }
+    let envelope = envelope?;
+
+    // magic, version 0, then flags for an XY envelope and little-endian byte order
+    let mut blob = vec![b'G', b'P', 0, 0b0000_0011];
+    blob.extend(&SRS_ID.to_le_bytes());
+    for v in envelope.iter() {
+        blob.extend(&v.to_le_bytes());
+    }
+    blob.extend(wkb);
+    Some((blob, envelope))
+}
+
+/// Writes the joined neighbourhoods to a new GeoPackage at `path`, with the same `name`,
+/// `population`, `covid_case_count` and `cases_per_100k` attributes as the GeoJSON properties.
+/// Features `join` didn't name are skipped.
+pub fn write(path: &Path, neighbourhoods: &FeatureCollection) -> rusqlite::Result<()> {
+    populate(&mut Connection::open(path)?, neighbourhoods)
+}
+
+/// The size in bytes of the file `write` would produce, built in memory.
+pub fn size(neighbourhoods: &FeatureCollection) -> rusqlite::Result<u64> {
+    let mut db = Connection::open_in_memory()?;
+    populate(&mut db, neighbourhoods)?;
+    let pages: u64 = db.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: u64 = db.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok(pages * page_size)
+}
+
+fn populate(db: &mut Connection, neighbourhoods: &FeatureCollection) -> rusqlite::Result<()> {
+    db.pragma_update(None, "application_id", 0x4750_4B47)?;
+    db.pragma_update(None, "user_version", 10200)?;
+    let tx = db.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+    tx.execute(
+        "INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84 geodetic', ?1, 'EPSG', ?1, ?2, NULL)",
+        params![SRS_ID, WGS84],
+    )?;
+    tx.execute_batch(&format!(
+        "CREATE TABLE {} (
+            fid INTEGER PRIMARY KEY AUTOINCREMENT,
+            geom MULTIPOLYGON,
+            name TEXT NOT NULL,
+            population INTEGER,
+            covid_case_count INTEGER,
+            cases_per_100k DOUBLE
+        );",
+        TABLE
+    ))?;
+
+    let mut envelope = None;
+    {
+        let mut insert = tx.prepare(&format!(
+            "INSERT INTO {} (geom, name, population, covid_case_count, cases_per_100k)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            TABLE
+        ))?;
+        for feature in neighbourhoods.features.iter() {
+            let properties = match &feature.properties {
+                Some(properties) => properties,
+                None => continue,
+            };
+            let name = match properties.get("name").and_then(Value::as_str) {
+                Some(name) => name,
+                None => continue,
+            };
+            let blob = feature
+                .geometry
+                .as_ref()
+                .and_then(|g| geometry_blob(&g.value));
+            if let Some((_, e)) = &blob {
+                extend(&mut envelope, e[0], e[2]);
+                extend(&mut envelope, e[1], e[3]);
+            }
+            insert.execute(params![
+                blob.map(|(blob, _)| blob),
+                name,
+                properties.get("population").and_then(Value::as_u64),
+                properties.get("covid_case_count").and_then(Value::as_u64),
+                properties.get("cases_per_100k").and_then(Value::as_f64),
+            ])?;
+        }
+    }
+
+    let [min_x, max_x, min_y, max_y] = envelope.map_or([None; 4], |e| e.map(Some));
+    tx.execute(
+        "INSERT INTO gpkg_contents (table_name, data_type, identifier, min_x, min_y, max_x, max_y, srs_id)
+         VALUES (?1, 'features', ?1, ?2, ?3, ?4, ?5, ?6)",
+        params![TABLE, min_x, min_y, max_x, max_y, SRS_ID],
+    )?;
+    tx.execute(
+        "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geom', 'MULTIPOLYGON', ?2, 0, 0)",
+        params![TABLE, SRS_ID],
+    )?;
+    tx.commit()
+}
@@ -0,0 +1,2867 @@
+use chrono::{Datelike, NaiveDate};
+use geojson::{Feature, FeatureCollection, GeoJson};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+
+pub mod age;
+pub mod config;
+pub mod fetch;
+pub mod filter;
+pub mod geometry;
+pub mod gpkg;
+pub mod output;
+pub mod stream;
+pub mod svg;
+pub mod topojson;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CovidEntry {
+    /// Unique row identifier for Open Data database
+    #[serde(rename = "_id")]
+    pub id: u32,
+    /// Outbreak associated cases are associated with outbreaks of COVID-19 in Toronto healthcare
+    /// institutions and healthcare settings (e.g. long-term care homes, retirement homes,
+    /// hospitals, etc.) and other Toronto congregate settings (such as homeless shelters).
+    #[serde(rename = "Outbreak Associated")]
+    pub outbreak_associated: OutbreakAssociated,
+
+    /// Age at time of illness. Age groups (in years): ≤19, 20-29, 30-39, 40-49, 50-59, 60-69,
+    /// 70-79, 80-89, 90+, unknown.
+    #[serde(rename = "Age Group")]
+    pub age_group: Option<AgeGroup>,
+
+    /// Toronto is divided into 140 geographically distinct neighborhoods that were established to
+    /// help government and community agencies with local planning by providing socio-economic data
+    /// for a meaningful geographic area.
+    #[serde(rename = "Neighbourhood Name")]
+    pub neighbourhood: Option<String>,
+
+    /// Forward sortation area (i.e. first three characters of postal code) based on the case's
+    /// primary home address.
+    #[serde(rename = "FSA")]
+    pub fsa: Option<String>,
+
+    /// Client gender as reported, e.g. FEMALE, MALE, TRANSGENDER, OTHER, UNKNOWN.
+    #[serde(rename = "Client Gender")]
+    pub gender: Option<String>,
+
+    /// Fatal cases are those where the person died of or with COVID-19. Resolved cases are those
+    /// reported as recovered or more than 14 days past symptom onset and not hospitalized.
+    #[serde(rename = "Outcome")]
+    pub outcome: Option<Outcome>,
+
+    /// The earliest of symptom onset, test or reported date. Unparseable dates are read as `None`.
+    #[serde(rename = "Episode Date", default, deserialize_with = "lenient_date")]
+    pub episode_date: Option<NaiveDate>,
+
+    /// The date the case was reported to Toronto Public Health. Unparseable dates are read as
+    /// `None`.
+    #[serde(rename = "Reported Date", default, deserialize_with = "lenient_date")]
+    pub reported_date: Option<NaiveDate>,
+
+    /// Confirmed cases have a positive laboratory test. Probable cases are symptomatic and either
+    /// had high-risk exposure or an indeterminate test.
+    #[serde(rename = "Classification")]
+    pub classification: Option<Classification>,
+
+    /// Whether the case was ever hospitalized, ever in an ICU or ever intubated for COVID-19.
+    /// Blank or unexpected values are read as `None`.
+    #[serde(rename = "Ever Hospitalized", default, deserialize_with = "yes_no")]
+    pub ever_hospitalized: Option<bool>,
+    #[serde(rename = "Ever in ICU", default, deserialize_with = "yes_no")]
+    pub ever_icu: Option<bool>,
+    #[serde(rename = "Ever Intubated", default, deserialize_with = "yes_no")]
+    pub ever_intubated: Option<bool>,
+
+    /// The likely source of infection as reported, e.g. Close contact, Community, Travel or
+    /// N/A - Outbreak associated. See `source_category` for how these are grouped.
+    #[serde(rename = "Source of Infection", default)]
+    pub source_of_infection: Option<String>,
+
+    /// Every column not modeled above, by name, so new columns survive a round trip.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl CovidEntry {
+    pub fn is_confirmed(&self) -> bool {
+        self.classification == Some(Classification::Confirmed)
+    }
+
+    /// The neighbourhood the case is placed in, or `None` if it has none or is `NO_INFORMATION`.
+    pub fn named_neighbourhood(&self) -> Option<&str> {
+        self.neighbourhood
+            .as_deref()
+            .filter(|&n| normalize_for_match(n) != normalize_for_match(NO_INFORMATION))
+    }
+
+    /// Drops every extra column but those named in `keep`. `CaseCounts` tallies whichever extra
+    /// columns are left.
+    pub fn retain_extra(&mut self, keep: &[String]) {
+        self.extra.retain(|column, _| keep.contains(column));
+    }
+}
+
+fn lenient_date<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error> {
+    let date = Option::<String>::deserialize(deserializer)?;
+    Ok(date.and_then(|d| d.parse().ok()))
+}
+
+/// Every category `source_category` can return.
+pub const SOURCE_CATEGORIES: [&str; 9] = [
+    "Close Contact",
+    "Community",
+    "Outbreak",
+    "Travel",
+    "Healthcare",
+    "Institutional",
+    "Pending",
+    "No Information",
+    "Other",
+];
+
+/// Groups a source of infection into one of `SOURCE_CATEGORIES`, ignoring case and spelling
+/// differences between releases of the dataset. Blank sources have no information and anything
+/// unrecognized is Other.
+pub fn source_category(source: Option<&str>) -> &'static str {
+    let source = match source.map(str::trim) {
+        Some(source) if !source.is_empty() => source.to_lowercase(),
+        _ => return "No Information",
+    };
+    if source.contains("outbreak") {
+        "Outbreak"
+    } else if source.starts_with("close contact") {
+        "Close Contact"
+    } else if source.starts_with("community") {
+        "Community"
+    } else if source.starts_with("travel") {
+        "Travel"
+    } else if source.starts_with("healthcare") {
+        "Healthcare"
+    } else if source.starts_with("institutional") {
+        "Institutional"
+    } else if source.starts_with("pending") {
+        "Pending"
+    } else if source.starts_with("unknown") || source.starts_with("no information") {
+        "No Information"
+    } else {
+        "Other"
+    }
+}
+
+fn yes_no<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(match value.as_deref().map(str::trim) {
+        Some(v) if v.eq_ignore_ascii_case("yes") => Some(true),
+        Some(v) if v.eq_ignore_ascii_case("no") => Some(false),
+        _ => None,
+    })
+}
+
+/// An inclusive range of episode dates. Either end may be left open.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DateWindow {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl DateWindow {
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Whether an entry with this date falls in the window. Undated entries only pass an
+    /// unbounded window.
+    pub fn contains(&self, date: Option<NaiveDate>) -> bool {
+        match date {
+            Some(date) => {
+                self.since.is_none_or(|since| date >= since)
+                    && self.until.is_none_or(|until| date <= until)
+            }
+            None => self.is_unbounded(),
+        }
+    }
+
+    /// A short name for the window, for use in file names: `2021-03` for a calendar month, the
+    /// date for a single day, and `2021-03-01_2021-04-15` for any other range. Open ends give
+    /// `since-2021-03-01`, `until-2021-04-15` or `all`.
+    pub fn label(&self) -> String {
+        match (self.since, self.until) {
+            (Some(since), Some(until)) if since == until => since.to_string(),
+            (Some(since), Some(until)) => {
+                let month_end = since
+                    .checked_add_months(chrono::Months::new(1))
+                    .and_then(|next| next.pred_opt());
+                if since.day() == 1 && month_end == Some(until) {
+                    since.format("%Y-%m").to_string()
+                } else {
+                    format!("{}_{}", since, until)
+                }
+            }
+            (Some(since), None) => format!("since-{}", since),
+            (None, Some(until)) => format!("until-{}", until),
+            (None, None) => "all".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AgeGroup {
+    LtEq19,
+    R20to29,
+    R30to39,
+    R40to49,
+    R50to59,
+    R60to69,
+    R70to79,
+    R80to89,
+    R90Plus,
+    Unknown,
+}
+
+impl AgeGroup {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            // the published dataset has used both spellings for the youngest bucket
+            "≤19" | "19 and younger" => AgeGroup::LtEq19,
+            "20-29" => AgeGroup::R20to29,
+            "30-39" => AgeGroup::R30to39,
+            "40-49" => AgeGroup::R40to49,
+            "50-59" => AgeGroup::R50to59,
+            "60-69" => AgeGroup::R60to69,
+            "70-79" => AgeGroup::R70to79,
+            "80-89" => AgeGroup::R80to89,
+            "90+" => AgeGroup::R90Plus,
+            _ => AgeGroup::Unknown,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AgeGroup::LtEq19 => "≤19",
+            AgeGroup::R20to29 => "20-29",
+            AgeGroup::R30to39 => "30-39",
+            AgeGroup::R40to49 => "40-49",
+            AgeGroup::R50to59 => "50-59",
+            AgeGroup::R60to69 => "60-69",
+            AgeGroup::R70to79 => "70-79",
+            AgeGroup::R80to89 => "80-89",
+            AgeGroup::R90Plus => "90+",
+            AgeGroup::Unknown => "unknown",
+        }
+    }
+
+    /// The bucket an age in whole years falls into.
+    pub fn from_age(years: u32) -> Self {
+        match years {
+            0..=19 => AgeGroup::LtEq19,
+            20..=29 => AgeGroup::R20to29,
+            30..=39 => AgeGroup::R30to39,
+            40..=49 => AgeGroup::R40to49,
+            50..=59 => AgeGroup::R50to59,
+            60..=69 => AgeGroup::R60to69,
+            70..=79 => AgeGroup::R70to79,
+            80..=89 => AgeGroup::R80to89,
+            _ => AgeGroup::R90Plus,
+        }
+    }
+
+    /// Representative age of the bucket, suitable for weighted averages. The open-ended buckets
+    /// use a nominal width of ten years.
+    pub fn midpoint(self) -> Option<f64> {
+        match self {
+            AgeGroup::LtEq19 => Some(10.0),
+            AgeGroup::R20to29 => Some(25.0),
+            AgeGroup::R30to39 => Some(35.0),
+            AgeGroup::R40to49 => Some(45.0),
+            AgeGroup::R50to59 => Some(55.0),
+            AgeGroup::R60to69 => Some(65.0),
+            AgeGroup::R70to79 => Some(75.0),
+            AgeGroup::R80to89 => Some(85.0),
+            AgeGroup::R90Plus => Some(95.0),
+            AgeGroup::Unknown => None,
+        }
+    }
+}
+
+impl Serialize for AgeGroup {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+impl<'de> Deserialize<'de> for AgeGroup {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let label = String::deserialize(deserializer)?;
+        Ok(AgeGroup::from_label(&label))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OutbreakAssociated {
+    Outbreak,
+    Sporadic,
+    /// Any label we don't recognize, kept verbatim so it survives a round-trip.
+    Unknown(String),
+}
+
+impl OutbreakAssociated {
+    pub fn from_label(label: String) -> Self {
+        match label.as_str() {
+            "Outbreak Associated" => OutbreakAssociated::Outbreak,
+            "Sporadic" => OutbreakAssociated::Sporadic,
+            _ => OutbreakAssociated::Unknown(label),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            OutbreakAssociated::Outbreak => "Outbreak Associated",
+            OutbreakAssociated::Sporadic => "Sporadic",
+            OutbreakAssociated::Unknown(label) => label,
+        }
+    }
+}
+
+impl Serialize for OutbreakAssociated {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+impl<'de> Deserialize<'de> for OutbreakAssociated {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(OutbreakAssociated::from_label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Active,
+    Resolved,
+    Fatal,
+    /// Any label we don't recognize, kept verbatim so it survives a round-trip.
+    Unknown(String),
+}
+
+impl Outcome {
+    pub fn from_label(label: String) -> Self {
+        match label.as_str() {
+            "ACTIVE" => Outcome::Active,
+            "RESOLVED" => Outcome::Resolved,
+            "FATAL" => Outcome::Fatal,
+            _ => Outcome::Unknown(label),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Outcome::Active => "ACTIVE",
+            Outcome::Resolved => "RESOLVED",
+            Outcome::Fatal => "FATAL",
+            Outcome::Unknown(label) => label,
+        }
+    }
+}
+
+impl Serialize for Outcome {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+impl<'de> Deserialize<'de> for Outcome {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Outcome::from_label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Classification {
+    Confirmed,
+    Probable,
+    /// Any label we don't recognize, kept verbatim so it survives a round-trip.
+    Unknown(String),
+}
+
+impl Classification {
+    pub fn from_label(label: String) -> Self {
+        match label.as_str() {
+            "CONFIRMED" => Classification::Confirmed,
+            "PROBABLE" => Classification::Probable,
+            _ => Classification::Unknown(label),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Classification::Confirmed => "CONFIRMED",
+            Classification::Probable => "PROBABLE",
+            Classification::Unknown(label) => label,
+        }
+    }
+}
+
+impl Serialize for Classification {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+impl<'de> Deserialize<'de> for Classification {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Classification::from_label)
+    }
+}
+
+#[derive(Default)]
+pub struct CaseCounts {
+    pub total: u32,
+    pub outbreak: u32,
+    pub sporadic: u32,
+    pub male: u32,
+    pub female: u32,
+    /// Every case with a gender other than male or female.
+    pub other_gender: u32,
+    /// Cases with a blank or `UNKNOWN` gender.
+    pub unknown_gender: u32,
+    /// Cases whose outbreak association is neither outbreak nor sporadic.
+    pub unknown_outbreak: u32,
+    pub active: u32,
+    pub resolved: u32,
+    pub fatal: u32,
+    /// Cases with a blank or unrecognized outcome.
+    pub unknown_outcome: u32,
+    pub ever_hospitalized: u32,
+    pub ever_icu: u32,
+    pub ever_intubated: u32,
+    /// Cases by `source_category` of their source of infection.
+    pub sources: BTreeMap<&'static str, u32>,
+    /// Cases by age group. Cases without one are left out.
+    pub per_age_group: BTreeMap<AgeGroup, u32>,
+    /// Cases by the value of each of their extra columns. Blank values are left out.
+    pub extra: BTreeMap<String, BTreeMap<String, u32>>,
+    /// Cases by `(year, month)` of their episode date. Undated cases are left out.
+    pub per_month: BTreeMap<(i32, u32), u32>,
+    /// Cases by episode date. Undated cases are left out.
+    pub per_day: BTreeMap<NaiveDate, u32>,
+    /// The total days between episode and reported date, over the `reporting_lags` cases with a
+    /// plausible lag.
+    pub reporting_lag_days: i64,
+    pub reporting_lags: u32,
+    /// Cases with both dates whose lag was negative or longer than `MAX_REPORTING_LAG_DAYS`.
+    pub implausible_reporting_lags: u32,
+    /// The `_id` of every case, in the order they were added.
+    pub ids: Vec<u32>,
+}
+
+/// How the breakdowns of cases by age, gender, outcome, outbreak association and source of
+/// infection treat cases whose value is blank or unrecognized. `covid_case_count` counts every case
+/// whatever the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownPolicy {
+    /// Leave them out of the breakdowns altogether, shares included.
+    Exclude,
+    /// Count them in a bucket of their own, e.g. `covid_unknown_gender_count`.
+    #[default]
+    Separate,
+    /// Count them with the breakdown's catch-all bucket, `covid_cases_other` for gender and
+    /// Other for sources. Breakdowns without one leave them out, as with `Exclude`.
+    IncludeAsOther,
+}
+
+impl UnknownPolicy {
+    /// The name the policy is chosen by on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            UnknownPolicy::Exclude => "exclude",
+            UnknownPolicy::Separate => "separate",
+            UnknownPolicy::IncludeAsOther => "include-as-other",
+        }
+    }
+}
+
+impl std::str::FromStr for UnknownPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exclude" => Ok(UnknownPolicy::Exclude),
+            "separate" => Ok(UnknownPolicy::Separate),
+            "include-as-other" => Ok(UnknownPolicy::IncludeAsOther),
+            _ => Err(format!(
+                "unknown policy `{}`, expected exclude, separate or include-as-other",
+                s
+            )),
+        }
+    }
+}
+
+/// The longest believable delay between a case's episode and its report. Longer ones are taken
+/// to be data entry errors.
+pub const MAX_REPORTING_LAG_DAYS: i64 = 365;
+
+impl CaseCounts {
+    /// Adds another set of tallies, e.g. one built on a different thread, to this one.
+    pub fn merge(&mut self, other: &CaseCounts) {
+        self.total += other.total;
+        self.outbreak += other.outbreak;
+        self.sporadic += other.sporadic;
+        self.male += other.male;
+        self.female += other.female;
+        self.other_gender += other.other_gender;
+        self.unknown_gender += other.unknown_gender;
+        self.unknown_outbreak += other.unknown_outbreak;
+        self.active += other.active;
+        self.resolved += other.resolved;
+        self.fatal += other.fatal;
+        self.unknown_outcome += other.unknown_outcome;
+        self.ever_hospitalized += other.ever_hospitalized;
+        self.ever_icu += other.ever_icu;
+        self.ever_intubated += other.ever_intubated;
+        for (&source, &count) in other.sources.iter() {
+            *self.sources.entry(source).or_insert(0) += count;
+        }
+        for (&group, &count) in other.per_age_group.iter() {
+            *self.per_age_group.entry(group).or_insert(0) += count;
+        }
+        for (column, values) in other.extra.iter() {
+            let tally = self.extra.entry(column.clone()).or_default();
+            for (value, &count) in values.iter() {
+                *tally.entry(value.clone()).or_insert(0) += count;
+            }
+        }
+        for (&month, &count) in other.per_month.iter() {
+            *self.per_month.entry(month).or_insert(0) += count;
+        }
+        for (&day, &count) in other.per_day.iter() {
+            *self.per_day.entry(day).or_insert(0) += count;
+        }
+        self.reporting_lag_days += other.reporting_lag_days;
+        self.reporting_lags += other.reporting_lags;
+        self.implausible_reporting_lags += other.implausible_reporting_lags;
+        self.ids.extend_from_slice(&other.ids);
+    }
+
+    pub fn add(&mut self, entry: &CovidEntry) {
+        self.total += 1;
+        self.ids.push(entry.id);
+        match entry.outbreak_associated {
+            OutbreakAssociated::Outbreak => self.outbreak += 1,
+            OutbreakAssociated::Sporadic => self.sporadic += 1,
+            OutbreakAssociated::Unknown(_) => self.unknown_outbreak += 1,
+        }
+        match entry.gender.as_deref().map(str::trim) {
+            Some(g) if g.eq_ignore_ascii_case("male") => self.male += 1,
+            Some(g) if g.eq_ignore_ascii_case("female") => self.female += 1,
+            Some(g) if g.is_empty() || g.eq_ignore_ascii_case("unknown") => {
+                self.unknown_gender += 1
+            }
+            None => self.unknown_gender += 1,
+            Some(_) => self.other_gender += 1,
+        }
+        match entry.outcome {
+            Some(Outcome::Active) => self.active += 1,
+            Some(Outcome::Resolved) => self.resolved += 1,
+            Some(Outcome::Fatal) => self.fatal += 1,
+            Some(Outcome::Unknown(_)) | None => self.unknown_outcome += 1,
+        }
+        self.ever_hospitalized += (entry.ever_hospitalized == Some(true)) as u32;
+        self.ever_icu += (entry.ever_icu == Some(true)) as u32;
+        self.ever_intubated += (entry.ever_intubated == Some(true)) as u32;
+        let source = source_category(entry.source_of_infection.as_deref());
+        *self.sources.entry(source).or_insert(0) += 1;
+        if let Some(group) = entry.age_group {
+            *self.per_age_group.entry(group).or_insert(0) += 1;
+        }
+        for (column, value) in entry.extra.iter() {
+            let value = match value {
+                Value::Null => continue,
+                Value::String(s) if s.trim().is_empty() => continue,
+                Value::String(s) => s.trim().to_owned(),
+                value => value.to_string(),
+            };
+            *self
+                .extra
+                .entry(column.clone())
+                .or_default()
+                .entry(value)
+                .or_insert(0) += 1;
+        }
+        if let Some(date) = entry.episode_date {
+            *self
+                .per_month
+                .entry((date.year(), date.month()))
+                .or_insert(0) += 1;
+            *self.per_day.entry(date).or_insert(0) += 1;
+        }
+        if let (Some(episode), Some(reported)) = (entry.episode_date, entry.reported_date) {
+            let lag = (reported - episode).num_days();
+            if (0..=MAX_REPORTING_LAG_DAYS).contains(&lag) {
+                self.reporting_lag_days += lag;
+                self.reporting_lags += 1;
+            } else {
+                self.implausible_reporting_lags += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "CensusEntry", into = "CensusEntry")]
+pub enum CensusEntryCategory {
+    NeighbourhoodInformation(CensusEntry),
+    Other(CensusEntry),
+}
+
+impl CensusEntryCategory {
+    pub fn entry(&self) -> &CensusEntry {
+        match self {
+            CensusEntryCategory::NeighbourhoodInformation(e) | CensusEntryCategory::Other(e) => e,
+        }
+    }
+}
+
+impl CensusEntryCategory {
+    /// Whether a characteristic names the `NeighbourhoodInformation` row.
+    fn is_neighbourhood_information(characteristic: &str) -> bool {
+        characteristic == "Neighbourhood Number"
+    }
+}
+
+impl From<CensusEntry> for CensusEntryCategory {
+    fn from(entry: CensusEntry) -> Self {
+        if CensusEntryCategory::is_neighbourhood_information(&entry.characteristic) {
+            CensusEntryCategory::NeighbourhoodInformation(entry)
+        } else {
+            CensusEntryCategory::Other(entry)
+        }
+    }
+}
+
+impl From<CensusEntryCategory> for CensusEntry {
+    fn from(category: CensusEntryCategory) -> Self {
+        match category {
+            CensusEntryCategory::NeighbourhoodInformation(e) | CensusEntryCategory::Other(e) => e,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct CensusEntry {
+    #[serde(rename = "_id")]
+    pub id: u32,
+    /// The measured quantity. Nested characteristics are indented with leading spaces.
+    #[serde(rename = "Characteristic")]
+    pub characteristic: String,
+    #[serde(rename = "Category")]
+    pub category: String,
+    #[serde(rename = "Topic")]
+    pub topic: String,
+    #[serde(rename = "Data Source")]
+    pub data_source: String,
+    /// Every other column, by neighbourhood name.
+    #[serde(flatten)]
+    pub neighbourhoods: HashMap<String, Option<String>>,
+}
+
+// Written out rather than derived, since `#[serde(flatten)]` buffers every value of the row into
+// an intermediate map before it can be sorted into fields.
+impl<'de> Deserialize<'de> for CensusEntry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::DeserializeSeed;
+        let mut values = Vec::new();
+        let (id, Text(characteristic), Text(category), Text(topic), Text(source)) = RowSeed {
+            values: &mut values,
+        }
+        .deserialize(deserializer)?;
+        Ok(CensusEntry {
+            id,
+            characteristic: characteristic.into_owned(),
+            category: category.into_owned(),
+            topic: topic.into_owned(),
+            data_source: source.into_owned(),
+            neighbourhoods: into_neighbourhoods(&mut values),
+        })
+    }
+}
+
+/// The descriptive columns of a census row, without its per-neighbourhood values.
+#[derive(Debug, Clone, Copy)]
+pub struct CensusRow<'a> {
+    pub characteristic: &'a str,
+    pub category: &'a str,
+    pub topic: &'a str,
+}
+
+/// A string that borrows from the input unless it had escapes to undo.
+#[derive(Deserialize)]
+struct Text<'a>(#[serde(borrow)] Cow<'a, str>);
+
+/// Reads one census row into reusable storage, borrowing from the input where it can, so that
+/// deciding whether a row is wanted doesn't allocate.
+struct RowSeed<'b, 'de> {
+    values: &'b mut Vec<(Text<'de>, Option<Text<'de>>)>,
+}
+
+/// The columns of a census row other than its per-neighbourhood values: `_id`, `Characteristic`,
+/// `Category`, `Topic` and `Data Source`.
+type RowHeader<'de> = (u32, Text<'de>, Text<'de>, Text<'de>, Text<'de>);
+
+impl<'b, 'de> serde::de::DeserializeSeed<'de> for RowSeed<'b, 'de> {
+    type Value = RowHeader<'de>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'b, 'de> serde::de::Visitor<'de> for RowSeed<'b, 'de> {
+    type Value = RowHeader<'de>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a census row")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error;
+        self.values.clear();
+        let (mut id, mut characteristic, mut category, mut topic, mut source) =
+            (None, None, None, None, None);
+        while let Some(Text(key)) = map.next_key()? {
+            match key.as_ref() {
+                "_id" => id = Some(map.next_value()?),
+                "Characteristic" => characteristic = Some(map.next_value()?),
+                "Category" => category = Some(map.next_value()?),
+                "Topic" => topic = Some(map.next_value()?),
+                "Data Source" => source = Some(map.next_value()?),
+                _ => self.values.push((Text(key), map.next_value()?)),
+            }
+        }
+        Ok((
+            id.ok_or_else(|| A::Error::missing_field("_id"))?,
+            characteristic.ok_or_else(|| A::Error::missing_field("Characteristic"))?,
+            category.ok_or_else(|| A::Error::missing_field("Category"))?,
+            topic.ok_or_else(|| A::Error::missing_field("Topic"))?,
+            source.ok_or_else(|| A::Error::missing_field("Data Source"))?,
+        ))
+    }
+}
+
+/// Takes the per-neighbourhood values `RowSeed` read, leaving the storage to be reused.
+fn into_neighbourhoods(values: &mut Vec<(Text, Option<Text>)>) -> HashMap<String, Option<String>> {
+    values
+        .drain(..)
+        .map(|(Text(n), v)| (n.into_owned(), v.map(|Text(v)| v.into_owned())))
+        .collect()
+}
+
+struct RowsVisitor<'a, F> {
+    population_field: &'a str,
+    wanted: F,
+}
+
+impl<'de, F> serde::de::Visitor<'de> for RowsVisitor<'_, F>
+where
+    F: FnMut(&CensusRow) -> bool,
+{
+    type Value = Vec<CensusEntryCategory>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("an array of census rows")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut rows = Vec::new();
+        let mut values = Vec::new();
+        while let Some(header) = seq.next_element_seed(RowSeed {
+            values: &mut values,
+        })? {
+            let (id, Text(characteristic), Text(category), Text(topic), Text(source)) = header;
+            let row = CensusRow {
+                characteristic: &characteristic,
+                category: &category,
+                topic: &topic,
+            };
+            if !keep_row(&row, self.population_field, &mut self.wanted) {
+                continue;
+            }
+            let neighbourhoods = into_neighbourhoods(&mut values);
+            rows.push(CensusEntryCategory::from(CensusEntry {
+                id,
+                characteristic: characteristic.into_owned(),
+                category: category.into_owned(),
+                topic: topic.into_owned(),
+                data_source: source.into_owned(),
+                neighbourhoods,
+            }));
+        }
+        Ok(rows)
+    }
+}
+
+/// Parses the census, keeping the neighbourhood number rows, the rows whose `Characteristic` is
+/// `population_field`, as passed to `populations`, and whichever others `wanted` selects. Unwanted
+/// rows are skipped without building their map of neighbourhood values, which for most uses is
+/// nearly all of them.
+pub fn parse_census<F>(
+    json: &str,
+    population_field: &str,
+    wanted: F,
+) -> serde_json::Result<Vec<CensusEntryCategory>>
+where
+    F: FnMut(&CensusRow) -> bool,
+{
+    use serde::Deserializer;
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let rows = deserializer.deserialize_seq(RowsVisitor {
+        population_field,
+        wanted,
+    })?;
+    deserializer.end()?;
+    Ok(rows)
+}
+
+/// Whether `parse_census` keeps a row: always for the neighbourhood number and `population_field`
+/// rows, otherwise when `wanted` selects it. `wanted` is asked about every row either way.
+fn keep_row<F>(row: &CensusRow, population_field: &str, wanted: &mut F) -> bool
+where
+    F: FnMut(&CensusRow) -> bool,
+{
+    let always = CensusEntryCategory::is_neighbourhood_information(row.characteristic)
+        || row.characteristic.trim() == population_field.trim();
+    // asked first so that it sees every row, e.g. to count them
+    wanted(row) || always
+}
+
+/// The census columns `parse_census_csv` reads as something other than a neighbourhood value.
+const CENSUS_CSV_COLUMNS: [&str; 5] = ["_id", "Characteristic", "Category", "Topic", "Data Source"];
+
+/// Like `parse_census`, for the census as published: a CSV with a column per neighbourhood. Rows
+/// are numbered from 1 if there is no `_id` column, and blank values are read as missing.
+pub fn parse_census_csv<R, F>(
+    reader: R,
+    population_field: &str,
+    mut wanted: F,
+) -> csv::Result<Vec<CensusEntryCategory>>
+where
+    R: std::io::Read,
+    F: FnMut(&CensusRow) -> bool,
+{
+    let invalid = |message: String| {
+        csv::Error::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            message,
+        ))
+    };
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let required = |name: &str| {
+        column(name).ok_or_else(|| invalid(format!("census has no {:?} column", name)))
+    };
+    let id = column("_id");
+    let characteristic = required("Characteristic")?;
+    let category = required("Category")?;
+    let topic = required("Topic")?;
+    let source = required("Data Source")?;
+
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    let mut number = 0;
+    while reader.read_record(&mut record)? {
+        number += 1;
+        // `csv` rejects records with a different number of fields than the header
+        let row = CensusRow {
+            characteristic: &record[characteristic],
+            category: &record[category],
+            topic: &record[topic],
+        };
+        if !keep_row(&row, population_field, &mut wanted) {
+            continue;
+        }
+        let id = match id {
+            Some(id) => record[id]
+                .trim()
+                .parse()
+                .map_err(|e| invalid(format!("census row {}: bad _id: {}", number, e)))?,
+            None => number,
+        };
+        let neighbourhoods = headers
+            .iter()
+            .zip(record.iter())
+            .filter(|(header, _)| !CENSUS_CSV_COLUMNS.contains(header))
+            .map(|(header, value)| {
+                let value = Some(value).filter(|v| !v.trim().is_empty());
+                (header.to_owned(), value.map(str::to_owned))
+            })
+            .collect();
+        rows.push(CensusEntryCategory::from(CensusEntry {
+            id,
+            characteristic: record[characteristic].to_owned(),
+            category: record[category].to_owned(),
+            topic: record[topic].to_owned(),
+            data_source: record[source].to_owned(),
+            neighbourhoods,
+        }));
+    }
+    Ok(rows)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GeoJsonError {
+    #[error(
+        "neighbourhoods input is a bare geometry, which has no properties to attach counts to"
+    )]
+    BareGeometry,
+}
+
+/// Accepts either a FeatureCollection or a single Feature, which is wrapped in a collection.
+pub fn into_feature_collection(geojson: GeoJson) -> Result<FeatureCollection, GeoJsonError> {
+    match geojson {
+        GeoJson::FeatureCollection(collection) => Ok(collection),
+        GeoJson::Feature(feature) => Ok(FeatureCollection {
+            bbox: None,
+            features: vec![feature],
+            foreign_members: None,
+        }),
+        GeoJson::Geometry(_) => Err(GeoJsonError::BareGeometry),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CensusError {
+    #[error("census has no characteristic named {0:?}")]
+    MissingCharacteristic(String),
+    #[error(
+        "none of the {found} {field:?} row(s) in the census has category {:?} and topic {:?}",
+        POPULATION_CATEGORY,
+        POPULATION_TOPIC
+    )]
+    MissingPopulation { field: String, found: usize },
+    #[error(
+        "{found} {field:?} rows in the census have category {:?} and topic {:?}, expected one",
+        POPULATION_CATEGORY,
+        POPULATION_TOPIC
+    )]
+    AmbiguousPopulation { field: String, found: usize },
+}
+
+/// Parses a census figure such as `"2,731,571"` or `"$65,829"`.
+pub fn parse_census_number(raw: &str) -> Option<f64> {
+    raw.trim().replace([',', '$'], "").parse().ok()
+}
+
+/// Reads the per-neighbourhood values of an arbitrary census characteristic, keyed by normalized
+/// name. Leading indentation on the characteristic is ignored, and if several rows share the name
+/// the first one is used. Values that aren't numeric are left out.
+pub fn census_values(
+    census: &[CensusEntryCategory],
+    characteristic: &str,
+    names: &Names,
+) -> Result<HashMap<String, f64>, CensusError> {
+    let entry = census
+        .iter()
+        .map(CensusEntryCategory::entry)
+        .find(|e| e.characteristic.trim() == characteristic.trim())
+        .ok_or_else(|| CensusError::MissingCharacteristic(characteristic.to_owned()))?;
+    Ok(entry
+        .neighbourhoods
+        .iter()
+        .filter_map(|(n, value)| {
+            let value = parse_census_number(value.as_deref()?)?;
+            Some((names.canonicalize(n).to_owned(), value))
+        })
+        .collect())
+}
+
+/// Sets `key` on every feature to that neighbourhood's entry in `values`, or null if it has none.
+pub fn attach_values(
+    neighbourhoods: &mut FeatureCollection,
+    key: &str,
+    values: &HashMap<String, f64>,
+    names: &Names,
+) {
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(properties) = &mut feature.properties {
+            if let Some(name) = matched_name(properties, names) {
+                let v = values
+                    .get(&name)
+                    .map_or(serde_json::Value::Null, |&v| serde_json::Value::from(v));
+                properties.insert(key.to_owned(), v);
+            }
+        }
+    }
+}
+
+/// Sets `rate_per_100k` on every feature to its cases per 100,000 of its entry in `denominators`,
+/// e.g. a census characteristic such as households in place of the population, or null if it has
+/// none or it isn't positive.
+pub fn attach_rates(
+    neighbourhoods: &mut FeatureCollection,
+    per_neighbourhood: &HashMap<String, CaseCounts>,
+    denominators: &HashMap<String, f64>,
+    names: &Names,
+) {
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(properties) = &mut feature.properties {
+            if let Some(name) = matched_name(properties, names) {
+                let cases = per_neighbourhood.get(&name).map_or(0, |c| c.total);
+                let rate = denominators
+                    .get(&name)
+                    .filter(|&&d| d > 0.0)
+                    .map(|d| cases as f64 * 100_000.0 / d);
+                let v = rate.map_or(serde_json::Value::Null, serde_json::Value::from);
+                properties.insert("rate_per_100k".to_owned(), v);
+            }
+        }
+    }
+}
+
+/// Sets `covid_case_delta` and `covid_rate_delta` on every feature to the change in its case
+/// count and rate between an older and a newer tally. A neighbourhood missing from either tally
+/// counts as having no cases there.
+pub fn attach_deltas(
+    neighbourhoods: &mut FeatureCollection,
+    new: &HashMap<String, CaseCounts>,
+    old: &HashMap<String, CaseCounts>,
+    populations: &HashMap<String, u32>,
+    names: &Names,
+) {
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(properties) = &mut feature.properties {
+            if let Some(name) = matched_name(properties, names) {
+                let new = new.get(&name).map_or(0, |c| c.total);
+                let old = old.get(&name).map_or(0, |c| c.total);
+                let delta = new as i64 - old as i64;
+                properties.insert("covid_case_delta".to_owned(), delta.into());
+
+                let rate = populations
+                    .get(&name)
+                    .and_then(|&p| Some(rate_per_100k(new, p)? - rate_per_100k(old, p)?));
+                let v = rate.map_or(serde_json::Value::Null, serde_json::Value::from);
+                properties.insert("covid_rate_delta".to_owned(), v);
+            }
+        }
+    }
+}
+
+/// Sets `case_ids` on every matched feature to the `_id`s of the cases counted towards it, in
+/// ascending order and capped at the first `max`, and `case_ids_truncated` to whether any were left
+/// out.
+pub fn attach_case_ids(
+    neighbourhoods: &mut FeatureCollection,
+    per_neighbourhood: &HashMap<String, CaseCounts>,
+    max: usize,
+    names: &Names,
+) {
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(properties) = &mut feature.properties {
+            if let Some(name) = matched_name(properties, names) {
+                let mut ids = per_neighbourhood
+                    .get(&name)
+                    .map_or_else(Vec::new, |c| c.ids.clone());
+                ids.sort_unstable();
+                let truncated = ids.len() > max;
+                ids.truncate(max);
+                properties.insert("case_ids".to_owned(), ids.into());
+                properties.insert("case_ids_truncated".to_owned(), truncated.into());
+            }
+        }
+    }
+}
+
+/// Sets `rate_rank` and `rate_percentile` on every feature from its `cases_per_100k`. The highest
+/// rate is ranked 1 and tied rates share the best rank among them. The percentile is the share of
+/// the other ranked neighbourhoods with a lower rate, so the highest is 100 and the lowest 0.
+/// Features without a rate, i.e. without a population, are left out of the ranking and get null
+/// for both.
+pub fn attach_ranks(neighbourhoods: &mut FeatureCollection) {
+    let rate = |feature: &Feature| feature.properties.as_ref()?.get("cases_per_100k")?.as_f64();
+    let mut rates = neighbourhoods
+        .features
+        .iter()
+        .filter_map(rate)
+        .collect::<Vec<_>>();
+    rates.sort_by(|a, b| b.total_cmp(a));
+    let n = rates.len();
+    for feature in neighbourhoods.features.iter_mut() {
+        let rate = rate(feature);
+        let properties = match &mut feature.properties {
+            Some(properties) => properties,
+            None => continue,
+        };
+        let (rank, percentile) = match rate {
+            Some(rate) => {
+                let higher = rates.partition_point(|&r| r > rate);
+                let lower = n - rates.partition_point(|&r| r >= rate);
+                let percentile = if n > 1 {
+                    lower as f64 / (n - 1) as f64 * 100.0
+                } else {
+                    100.0
+                };
+                (Value::from(higher + 1), Value::from(percentile))
+            }
+            None => (Value::Null, Value::Null),
+        };
+        properties.insert("rate_rank".to_owned(), rank);
+        properties.insert("rate_percentile".to_owned(), percentile);
+    }
+}
+
+/// Sets each (multi)polygon feature's `bbox` member to `geometry::bbox`, and copies it to a `bbox`
+/// property for clients that only read properties. Features with another geometry are left alone.
+pub fn attach_bboxes(neighbourhoods: &mut FeatureCollection) {
+    for feature in neighbourhoods.features.iter_mut() {
+        let bbox = match feature
+            .geometry
+            .as_ref()
+            .and_then(|g| geometry::bbox(&g.value))
+        {
+            Some(bbox) => bbox.to_vec(),
+            None => continue,
+        };
+        if let Some(properties) = &mut feature.properties {
+            properties.insert("bbox".to_owned(), bbox.clone().into());
+        }
+        feature.bbox = Some(bbox);
+    }
+}
+
+/// The `NeighbourhoodSummary` properties that don't depend on case counts.
+const CONTEXT_PROPERTIES: [&str; 5] = [
+    "name",
+    "neighbourhood_number",
+    "population",
+    "population_density",
+    "age_standardization_fallback",
+];
+
+/// Hides the case figures of every neighbourhood with fewer than `below` cases, so small counts
+/// can't identify anyone on a public map. Every property derived from the neighbourhood's cases,
+/// including its deltas, case ids and the tallies of the `extra` columns, is set to null, and
+/// `suppressed` is set on every feature to say whether this happened. The suppressed cases still
+/// count towards the citywide total. Returns the suppressed neighbourhoods.
+pub fn suppress_small_counts(
+    neighbourhoods: &mut FeatureCollection,
+    below: u32,
+    extra: &[String],
+) -> Vec<String> {
+    let summary = NeighbourhoodSummary::new(
+        String::new(),
+        &CaseCounts::default(),
+        None,
+        None,
+        None,
+        DEFAULT_RATE_PER,
+        UnknownPolicy::Separate,
+    );
+    let summary = match serde_json::to_value(summary) {
+        Ok(Value::Object(summary)) => summary,
+        _ => unreachable!("a summary serializes to an object"),
+    };
+    let case_properties = summary
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !CONTEXT_PROPERTIES.contains(key))
+        .chain([
+            "covid_case_delta",
+            "covid_rate_delta",
+            "rate_per_100k",
+            "rate_rank",
+            "rate_percentile",
+            "case_ids",
+            "case_ids_truncated",
+        ])
+        .chain(extra.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+
+    let mut suppressed = Vec::new();
+    for feature in neighbourhoods.features.iter_mut() {
+        let properties = match &mut feature.properties {
+            Some(properties) => properties,
+            None => continue,
+        };
+        let cases = match properties.get("covid_case_count").and_then(Value::as_u64) {
+            Some(cases) => cases,
+            None => continue,
+        };
+        let suppress = cases < below as u64;
+        if suppress {
+            for &key in case_properties.iter() {
+                if let Some(value) = properties.get_mut(key) {
+                    *value = Value::Null;
+                }
+            }
+            if let Some(Value::String(name)) = properties.get("name") {
+                suppressed.push(name.clone());
+            }
+        }
+        properties.insert("suppressed".to_owned(), suppress.into());
+    }
+    suppressed
+}
+
+/// The feature `join` matched to a neighbourhood, given any known spelling of its name or one
+/// within the fuzzy threshold of a known name.
+pub fn find_feature<'a>(
+    neighbourhoods: &'a FeatureCollection,
+    name: &str,
+    names: &Names,
+) -> Option<&'a Feature> {
+    let find = |name: &str| {
+        let name = Some(normalize_for_match(name));
+        neighbourhoods.features.iter().find(|feature| {
+            let matched = feature.properties.as_ref();
+            let matched = matched.and_then(|p| matched_name(p, names));
+            matched.map(|m| normalize_for_match(&m)) == name
+        })
+    };
+    let name = names.canonicalize(name.trim());
+    find(name).or_else(|| find(&names.fuzzy_substitute(name)?.0))
+}
+
+/// The name `join` matched a feature under, or `None` if it was skipped.
+fn matched_name(properties: &serde_json::Map<String, Value>, names: &Names) -> Option<String> {
+    let name = get_name(properties, names).ok()?;
+    let candidate = names.fuzzy_substitute(&name).map(|(c, _)| c);
+    Some(candidate.unwrap_or(name))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NameError {
+    #[error("feature has no AREA_NAME property")]
+    MissingAreaName,
+    #[error("feature's AREA_NAME is not a string")]
+    NotAString,
+}
+
+/// Reads the normalized neighbourhood name from a feature's properties.
+pub fn get_name(
+    data: &serde_json::Map<String, serde_json::Value>,
+    names: &Names,
+) -> Result<String, NameError> {
+    let name = match data.get("AREA_NAME").ok_or(NameError::MissingAreaName)? {
+        Value::String(str) => str,
+        _ => return Err(NameError::NotAString),
+    };
+    // munge the name to make it match with the covid data
+    let name = name.split(" (").next().unwrap_or(name);
+    Ok(names.canonicalize(name).to_owned())
+}
+
+/// The `Characteristic` of the census row holding each neighbourhood's population, unless another
+/// is passed to `populations`.
+pub const DEFAULT_POPULATION_FIELD: &str = "Population, 2016";
+/// The `Category` and `Topic` of the census row holding each neighbourhood's population.
+pub const POPULATION_CATEGORY: &str = "Population";
+pub const POPULATION_TOPIC: &str = "Population and dwellings";
+
+/// Extracts the population of each neighbourhood, keyed by normalized name, from the row whose
+/// `Characteristic` is `field`, e.g. `"Population, 2021"` for a later census. Leading and trailing
+/// whitespace is ignored. Exactly one such row must belong to the expected category and topic.
+/// `parse_census` keeps it when given the same `field`.
+pub fn populations(
+    census: Vec<CensusEntryCategory>,
+    names: &Names,
+    field: &str,
+) -> Result<HashMap<String, u32>, CensusError> {
+    let field = field.trim();
+    let candidates = census
+        .into_iter()
+        .map(CensusEntry::from)
+        .filter(|e| e.characteristic.trim() == field)
+        .collect::<Vec<_>>();
+    let found = candidates.len();
+    let mut matching = candidates
+        .into_iter()
+        .filter(|e| e.category == POPULATION_CATEGORY && e.topic == POPULATION_TOPIC)
+        .collect::<Vec<_>>();
+    let populations = match matching.len() {
+        1 => matching.remove(0),
+        0 => {
+            let field = field.to_owned();
+            return Err(CensusError::MissingPopulation { field, found });
+        }
+        found => {
+            let field = field.to_owned();
+            return Err(CensusError::AmbiguousPopulation { field, found });
+        }
+    };
+    Ok(populations
+        .neighbourhoods
+        .into_iter()
+        .filter_map(|(n, pop)| {
+            if let Some(pop) = pop {
+                match pop.replace(",", "").parse::<u32>() {
+                    Ok(parsed) => Some((names.canonicalize(&n).to_owned(), parsed)),
+                    Err(e) => {
+                        log::warn!("ignoring unparseable population {:?} for {}: {}", pop, n, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// Each neighbourhood's number, from the census's `NeighbourhoodInformation` row, keyed by
+/// normalized name. Values that aren't whole numbers are left out.
+pub fn neighbourhood_numbers(
+    census: &[CensusEntryCategory],
+    names: &Names,
+) -> HashMap<String, u32> {
+    let entry = census.iter().find_map(|c| match c {
+        CensusEntryCategory::NeighbourhoodInformation(e) => Some(e),
+        _ => None,
+    });
+    entry.map_or_else(HashMap::new, |entry| {
+        entry
+            .neighbourhoods
+            .iter()
+            .filter_map(|(n, number)| {
+                let number = number.as_deref()?.trim().parse().ok()?;
+                Some((names.canonicalize(n).to_owned(), number))
+            })
+            .collect()
+    })
+}
+
+/// The parts of the census joined onto every feature, each keyed by normalized name.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CensusData {
+    pub populations: HashMap<String, u32>,
+    pub age_bands: HashMap<String, age::AgeBands>,
+    pub numbers: HashMap<String, u32>,
+}
+
+impl CensusData {
+    pub fn new(
+        census: Vec<CensusEntryCategory>,
+        names: &Names,
+        population_field: &str,
+    ) -> Result<Self, CensusError> {
+        let age_bands = age::age_bands(&census, names);
+        let numbers = neighbourhood_numbers(&census, names);
+        Ok(CensusData {
+            populations: populations(census, names, population_field)?,
+            age_bands,
+            numbers,
+        })
+    }
+}
+
+/// Everything tallied from a single pass over the COVID data.
+#[derive(Default)]
+pub struct Aggregates {
+    /// Keyed by normalized name. Entries without a neighbourhood are not counted.
+    pub per_neighbourhood: HashMap<String, CaseCounts>,
+    /// Entries without a neighbourhood, either blank or `NO_INFORMATION`.
+    pub unassigned: u32,
+    /// Keyed by forward sortation area. FSAs are treated as opaque, uppercased strings and entries
+    /// without one are not counted.
+    pub per_fsa: HashMap<String, u32>,
+    /// Cases by forward sortation area and then by the normalized name of their neighbourhood, for
+    /// relating the two. Entries missing either are not counted.
+    pub per_fsa_neighbourhood: HashMap<String, BTreeMap<String, u32>>,
+    /// Every entry seen, whether or not it could be placed.
+    pub total: u32,
+    /// Entries that named a neighbourhood, whether or not it matched a feature.
+    pub with_neighbourhood: u32,
+}
+
+/// The neighbourhood the COVID data gives cases that couldn't be geolocated. It has no boundary.
+pub const NO_INFORMATION: &str = "No Information";
+
+impl Aggregates {
+    pub fn add(&mut self, entry: &CovidEntry, names: &Names) {
+        self.total += 1;
+        let neighbourhood = entry.named_neighbourhood();
+        match neighbourhood {
+            Some(neighbourhood) => {
+                self.with_neighbourhood += 1;
+                self.per_neighbourhood
+                    .entry(names.canonicalize(neighbourhood).to_owned())
+                    .or_default()
+                    .add(entry);
+            }
+            None => self.unassigned += 1,
+        }
+        if let Some(fsa) = &entry.fsa {
+            *self.per_fsa.entry(fsa.to_uppercase()).or_insert(0) += 1;
+        }
+        if let (Some(fsa), Some(neighbourhood)) = (&entry.fsa, neighbourhood) {
+            *self
+                .per_fsa_neighbourhood
+                .entry(fsa.to_uppercase())
+                .or_default()
+                .entry(names.canonicalize(neighbourhood).to_owned())
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// Adds another set of tallies, e.g. one built on a different thread, to this one.
+    pub fn merge(&mut self, other: Aggregates) {
+        for (name, counts) in other.per_neighbourhood {
+            self.per_neighbourhood
+                .entry(name)
+                .or_default()
+                .merge(&counts);
+        }
+        for (fsa, count) in other.per_fsa {
+            *self.per_fsa.entry(fsa).or_insert(0) += count;
+        }
+        for (fsa, neighbourhoods) in other.per_fsa_neighbourhood {
+            let tally = self.per_fsa_neighbourhood.entry(fsa).or_default();
+            for (name, count) in neighbourhoods {
+                *tally.entry(name).or_insert(0) += count;
+            }
+        }
+        self.total += other.total;
+        self.unassigned += other.unassigned;
+        self.with_neighbourhood += other.with_neighbourhood;
+    }
+}
+
+/// Whether a forward sortation area has the shape of a Canadian one: letter, digit, letter, e.g.
+/// `M5V`. Either case is accepted.
+pub fn is_valid_fsa(s: &str) -> bool {
+    let s = s.as_bytes();
+    s.len() == 3
+        && s[0].is_ascii_alphabetic()
+        && s[1].is_ascii_digit()
+        && s[2].is_ascii_alphabetic()
+}
+
+impl Aggregates {
+    /// Every counted FSA that fails `is_valid_fsa`, with its case count, sorted.
+    pub fn invalid_fsas(&self) -> Vec<(&str, u32)> {
+        let mut invalid = self
+            .per_fsa
+            .iter()
+            .filter(|(fsa, _)| !is_valid_fsa(fsa))
+            .map(|(fsa, &count)| (fsa.as_str(), count))
+            .collect::<Vec<_>>();
+        invalid.sort_unstable();
+        invalid
+    }
+
+    /// Drops the FSAs that fail `is_valid_fsa` from the FSA tallies. The cases still count
+    /// everywhere else.
+    pub fn exclude_invalid_fsas(&mut self) {
+        self.per_fsa.retain(|fsa, _| is_valid_fsa(fsa));
+        self.per_fsa_neighbourhood
+            .retain(|fsa, _| is_valid_fsa(fsa));
+    }
+}
+
+/// Tallies the COVID data in parallel as it is produced, so the entries never need to be held in
+/// memory at once.
+pub fn aggregate<I>(covid: I, names: &Names) -> Aggregates
+where
+    I: IntoIterator<Item = CovidEntry>,
+    I::IntoIter: Send,
+{
+    covid
+        .into_iter()
+        .par_bridge()
+        .fold(Aggregates::default, |mut aggregates, e| {
+            aggregates.add(&e, names);
+            aggregates
+        })
+        .reduce(Aggregates::default, |mut a, b| {
+            a.merge(b);
+            a
+        })
+}
+
+/// The properties `join` adds to each feature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeighbourhoodSummary {
+    pub name: String,
+    /// The census's number for the neighbourhood.
+    pub neighbourhood_number: Option<u32>,
+    pub population: Option<u32>,
+    /// Residents per square kilometre.
+    pub population_density: Option<f64>,
+    pub covid_case_count: u32,
+    pub cases_per_100k: Option<f64>,
+    /// Cases per the collection's `rate_per` residents.
+    pub covid_rate: Option<f64>,
+    /// The share of residents who have been a case, as a percentage. See
+    /// `cumulative_incidence_pct`.
+    pub cumulative_incidence_pct: Option<f64>,
+    /// Cases per 100,000 residents, directly standardized to the city's age structure. See
+    /// `age::standardized_rate`.
+    pub cases_age_standardized_per_100k: Option<f64>,
+    /// Set when the age-standardized rate couldn't be computed, e.g. for lack of census age bands,
+    /// and `cases_age_standardized_per_100k` holds the crude rate instead.
+    pub age_standardization_fallback: bool,
+    pub covid_outbreak_count: u32,
+    /// The fraction of cases that were outbreak associated.
+    pub covid_outbreak_share: Option<f64>,
+    pub covid_sporadic_count: u32,
+    pub covid_cases_male: u32,
+    pub covid_cases_female: u32,
+    pub covid_cases_other: u32,
+    /// Cases with a blank or unknown gender. Only counted under `UnknownPolicy::Separate`.
+    pub covid_unknown_gender_count: Option<u32>,
+    /// Cases neither outbreak associated nor sporadic. Only counted under
+    /// `UnknownPolicy::Separate`.
+    pub covid_unknown_outbreak_count: Option<u32>,
+    pub covid_active: u32,
+    pub covid_resolved: u32,
+    pub covid_fatal: u32,
+    pub covid_cfr: Option<f64>,
+    /// Cases without a known outcome. Only counted under `UnknownPolicy::Separate`.
+    pub covid_unknown_outcome_count: Option<u32>,
+    /// The mean age of cases, estimated from their age groups. See `age::mean_age`.
+    pub covid_mean_case_age: Option<f64>,
+    /// Cases without a known age group. Only counted under `UnknownPolicy::Separate`.
+    pub covid_unknown_age_count: Option<u32>,
+    /// The mean days from episode to report, over cases with a plausible lag.
+    pub mean_reporting_lag_days: Option<f64>,
+    /// Cases left out of `mean_reporting_lag_days` for a negative or implausibly long lag.
+    pub reporting_lag_excluded_count: u32,
+    /// The change in cases from the week before the last one in the data to the last. See
+    /// `weekly_growth_pct`.
+    pub weekly_growth_pct: Option<f64>,
+    pub ever_hospitalized: u32,
+    pub ever_icu: u32,
+    pub ever_intubated: u32,
+    /// Cases by `source_category`.
+    pub sources: BTreeMap<String, u32>,
+}
+
+impl NeighbourhoodSummary {
+    pub fn new(
+        name: String,
+        counts: &CaseCounts,
+        population: Option<u32>,
+        area_km2: Option<f64>,
+        age_standardized: Option<f64>,
+        rate_per: u32,
+        policy: UnknownPolicy,
+    ) -> Self {
+        let population_density = population
+            .zip(area_km2)
+            .filter(|&(_, area)| area > 0.0)
+            .map(|(population, area)| population as f64 / area);
+        let cases_per_100k = population.and_then(|p| rate_per_100k(counts.total, p));
+        let separate = |count: u32| Some(count).filter(|_| policy == UnknownPolicy::Separate);
+        let (outbreak_denominator, other_gender) = match policy {
+            UnknownPolicy::Exclude => (counts.outbreak + counts.sporadic, counts.other_gender),
+            UnknownPolicy::Separate => (counts.total, counts.other_gender),
+            UnknownPolicy::IncludeAsOther => {
+                (counts.total, counts.other_gender + counts.unknown_gender)
+            }
+        };
+        let mut sources = counts
+            .sources
+            .iter()
+            .map(|(&source, &count)| (source.to_owned(), count))
+            .collect::<BTreeMap<_, _>>();
+        if policy != UnknownPolicy::Separate {
+            if let Some(count) = sources.remove("No Information") {
+                if policy == UnknownPolicy::IncludeAsOther {
+                    *sources.entry("Other".to_owned()).or_insert(0) += count;
+                }
+            }
+        }
+        NeighbourhoodSummary {
+            name,
+            neighbourhood_number: None,
+            population,
+            population_density,
+            covid_case_count: counts.total,
+            cases_per_100k,
+            covid_rate: population.and_then(|p| crate::rate_per(counts.total, p, rate_per)),
+            cumulative_incidence_pct: population
+                .and_then(|p| cumulative_incidence_pct(counts.total, p)),
+            cases_age_standardized_per_100k: age_standardized.or(cases_per_100k),
+            age_standardization_fallback: age_standardized.is_none(),
+            covid_outbreak_count: counts.outbreak,
+            covid_outbreak_share: outbreak_share(counts.outbreak, outbreak_denominator),
+            covid_sporadic_count: counts.sporadic,
+            covid_cases_male: counts.male,
+            covid_cases_female: counts.female,
+            covid_cases_other: other_gender,
+            covid_unknown_gender_count: separate(counts.unknown_gender),
+            covid_unknown_outbreak_count: separate(counts.unknown_outbreak),
+            covid_active: counts.active,
+            covid_resolved: counts.resolved,
+            covid_fatal: counts.fatal,
+            covid_cfr: case_fatality_rate(counts.fatal, counts.resolved),
+            covid_unknown_outcome_count: separate(counts.unknown_outcome),
+            covid_mean_case_age: age::mean_age(&counts.per_age_group),
+            covid_unknown_age_count: separate(age::unknown_age_count(
+                counts.total,
+                &counts.per_age_group,
+            )),
+            mean_reporting_lag_days: mean_reporting_lag(counts),
+            reporting_lag_excluded_count: counts.implausible_reporting_lags,
+            weekly_growth_pct: None,
+            ever_hospitalized: counts.ever_hospitalized,
+            ever_icu: counts.ever_icu,
+            ever_intubated: counts.ever_intubated,
+            sources,
+        }
+    }
+}
+
+/// Feature names that had no counterpart in one of the joined datasets.
+#[derive(Default)]
+pub struct Unmatched {
+    /// Features with no COVID data whose name isn't a known neighbourhood either, so the missing
+    /// data is likely a naming problem.
+    pub covid: Vec<String>,
+    /// Known neighbourhoods absent from the COVID data. These are counted as having no cases.
+    pub no_cases: Vec<String>,
+    pub census: Vec<String>,
+    /// Features skipped because their name couldn't be read, with their `_id` if they had one.
+    pub unnamed: Vec<(Option<Value>, NameError)>,
+    /// Unknown feature names that were substituted with their closest known name, with the edit
+    /// distance between them.
+    pub fuzzy: Vec<(String, String, usize)>,
+    /// Neighbourhoods in the COVID data that no feature matched, sorted by name.
+    pub features: Vec<String>,
+    /// Features whose `AREA_SHORT_CODE` disagrees with the census's neighbourhood number, as
+    /// `(name, AREA_SHORT_CODE, census number)`.
+    pub numbers: Vec<(String, Value, u32)>,
+}
+
+impl Unmatched {
+    /// Every mismatch, one `Issue` per neighbourhood or feature.
+    pub fn issues(&self) -> Vec<Issue> {
+        let named = |category, names: &[String], detail: &str| {
+            names
+                .iter()
+                .map(|name| Issue::new(category, Some(name), detail.to_owned()))
+                .collect::<Vec<_>>()
+        };
+        let mut issues = Vec::new();
+        for (id, e) in self.unnamed.iter() {
+            let detail = match id {
+                Some(id) => format!("skipped feature {}: {}", id, e),
+                None => format!("skipped feature without an _id: {}", e),
+            };
+            issues.push(Issue::new("unnamed_feature", None, detail));
+        }
+        for (name, candidate, distance) in self.fuzzy.iter() {
+            let detail = format!(
+                "using closest known name {}, {} edit(s) away",
+                candidate, distance
+            );
+            issues.push(Issue::new("fuzzy_match", Some(name), detail));
+        }
+        issues.extend(named(
+            "no_cases",
+            &self.no_cases,
+            "counted as having no cases",
+        ));
+        issues.extend(named(
+            "no_covid_data",
+            &self.covid,
+            "not a known neighbourhood",
+        ));
+        issues.extend(named(
+            "no_census_population",
+            &self.census,
+            "no census population",
+        ));
+        for (name, code, number) in self.numbers.iter() {
+            let detail = format!(
+                "AREA_SHORT_CODE {} but neighbourhood number {} in the census",
+                code, number
+            );
+            issues.push(Issue::new("neighbourhood_number", Some(name), detail));
+        }
+        issues.extend(named(
+            "no_feature",
+            &self.features,
+            "in the COVID data but matched no feature",
+        ));
+        issues
+    }
+}
+
+/// Something worth a warning that came up during a run, for `--report`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    /// A short, stable identifier for the kind of problem, e.g. `no_feature`.
+    pub category: &'static str,
+    pub neighbourhood: Option<String>,
+    pub detail: String,
+}
+
+impl Issue {
+    pub fn new(category: &'static str, neighbourhood: Option<&str>, detail: String) -> Self {
+        Issue {
+            category,
+            neighbourhood: neighbourhood.map(str::to_owned),
+            detail,
+        }
+    }
+}
+
+/// Attaches a `NeighbourhoodSummary` to the properties of every feature.
+pub fn join(
+    neighbourhoods: &mut FeatureCollection,
+    per_neighbourhood_count: &HashMap<String, CaseCounts>,
+    census: &CensusData,
+    rate_per: u32,
+    policy: UnknownPolicy,
+    names: &Names,
+) -> Unmatched {
+    let mut unmatched = Unmatched::default();
+    let standard = census.age_bands.get(CITY_OF_TORONTO);
+    let no_cases = CaseCounts::default();
+    let mut matched = HashSet::new();
+    // the span of episode dates over every neighbourhood, so each one's weeks line up
+    let days = || {
+        per_neighbourhood_count
+            .values()
+            .flat_map(|counts| counts.per_day.keys())
+    };
+    let window = days().min().copied().zip(days().max().copied());
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(properties) = &mut feature.properties {
+            let name = match get_name(properties, names) {
+                Ok(name) => match names.fuzzy_substitute(&name) {
+                    Some((candidate, distance)) => {
+                        unmatched.fuzzy.push((name, candidate.clone(), distance));
+                        candidate
+                    }
+                    None => name,
+                },
+                Err(e) => {
+                    unmatched.unnamed.push((properties.get("_id").cloned(), e));
+                    continue;
+                }
+            };
+
+            matched.insert(name.clone());
+            let counts = per_neighbourhood_count.get(&name).unwrap_or_else(|| {
+                if names.is_known(&name) {
+                    unmatched.no_cases.push(name.clone());
+                } else {
+                    unmatched.covid.push(name.clone());
+                }
+                &no_cases
+            });
+            let population = census.populations.get(&name).copied();
+            if population.is_none() {
+                unmatched.census.push(name.clone());
+            }
+            let number = census.numbers.get(&name).copied();
+            if let Some((number, code)) = number.zip(properties.get("AREA_SHORT_CODE")) {
+                let matches = match code {
+                    Value::Number(code) => code.as_u64() == Some(number.into()),
+                    Value::String(code) => code.trim().parse() == Ok(number),
+                    _ => false,
+                };
+                if !matches {
+                    unmatched.numbers.push((name.clone(), code.clone(), number));
+                }
+            }
+            let area = feature
+                .geometry
+                .as_ref()
+                .and_then(|g| geometry::area_km2(&g.value));
+
+            let age_standardized = standard
+                .zip(census.age_bands.get(&name))
+                .and_then(|(s, p)| age::standardized_rate(&counts.per_age_group, p, s));
+
+            let mut summary = NeighbourhoodSummary::new(
+                name,
+                counts,
+                population,
+                area,
+                age_standardized,
+                rate_per,
+                policy,
+            );
+            summary.neighbourhood_number = number;
+            summary.weekly_growth_pct =
+                window.and_then(|(first, last)| weekly_growth_pct(&counts.per_day, first, last));
+            if let Ok(serde_json::Value::Object(summary)) = serde_json::to_value(summary) {
+                properties.extend(summary);
+            }
+            for (column, values) in counts.extra.iter() {
+                let values = values.iter().map(|(v, &count)| (v.clone(), count.into()));
+                properties.insert(column.clone(), Value::Object(values.collect()));
+            }
+        }
+    }
+    unmatched.features = per_neighbourhood_count
+        .keys()
+        .filter(|name| !matched.contains(*name))
+        .cloned()
+        .collect();
+    unmatched.features.sort();
+    unmatched
+}
+
+/// Compares the normalized feature names against `NEIGHBOURHOOD_NAMES`, returning every name that
+/// appears on only one side.
+pub fn validate_names(features: &[Feature], names: &Names) -> Vec<String> {
+    let feature_names = features
+        .iter()
+        .filter_map(|f| f.properties.as_ref())
+        .filter_map(|p| get_name(p, names).ok())
+        .collect::<HashSet<_>>();
+    let known_names = names.known().iter().cloned().collect::<HashSet<_>>();
+
+    let mut unmatched = feature_names
+        .symmetric_difference(&known_names)
+        .cloned()
+        .collect::<Vec<_>>();
+    unmatched.sort();
+    unmatched
+}
+
+/// The maximum edit distance at which a feature name is substituted with a known name, unless
+/// `Names::with_fuzzy_threshold` chose another.
+pub const FUZZY_THRESHOLD: usize = 2;
+
+/// The closest candidate by Levenshtein distance, with the distance, provided it is within
+/// `threshold` edits. Ties go to the earliest candidate.
+pub fn best_match<'a, S: AsRef<str>>(
+    name: &str,
+    candidates: &'a [S],
+    threshold: usize,
+) -> Option<(&'a str, usize)> {
+    candidates
+        .iter()
+        .map(|c| (c.as_ref(), strsim::levenshtein(name, c.as_ref())))
+        .filter(|&(_, distance)| distance <= threshold)
+        .min_by_key(|&(_, distance)| distance)
+}
+
+impl Names {
+    /// The `best_match` among the known names for a name that isn't itself known, within the
+    /// fuzzy threshold in use. Each name is only compared against the known names the first time
+    /// it's looked up, so `join` and every later step matching the same feature share the result.
+    fn fuzzy_substitute(&self, name: &str) -> Option<(String, usize)> {
+        if self.is_known(name) {
+            return None;
+        }
+        let threshold = self.fuzzy_threshold?;
+        let mut fuzzy_matches = self.fuzzy_matches.lock().unwrap_or_else(|e| e.into_inner());
+        fuzzy_matches
+            .entry(name.to_owned())
+            .or_insert_with(|| {
+                best_match(name, &self.known, threshold).map(|(c, d)| (c.to_owned(), d))
+            })
+            .clone()
+    }
+}
+
+/// How many COVID rows made it onto a feature.
+#[derive(Debug, Clone, Copy)]
+pub struct Coverage {
+    pub total: u32,
+    pub with_neighbourhood: u32,
+    /// Rows without a neighbourhood, or whose neighbourhood matched no feature.
+    pub dropped: u32,
+    pub matched: u32,
+}
+
+impl Coverage {
+    pub fn new(aggregates: &Aggregates, unmatched: &Unmatched) -> Self {
+        let unplaced: u32 = unmatched
+            .features
+            .iter()
+            .filter_map(|name| aggregates.per_neighbourhood.get(name))
+            .map(|counts| counts.total)
+            .sum();
+        let matched = aggregates.with_neighbourhood - unplaced;
+        Coverage {
+            total: aggregates.total,
+            with_neighbourhood: aggregates.with_neighbourhood,
+            dropped: aggregates.total - matched,
+            matched,
+        }
+    }
+
+    /// The share of rows that were matched, as a percentage. An empty dataset is fully covered.
+    pub fn percent_matched(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.matched as f64 / self.total as f64 * 100.0
+        }
+    }
+}
+
+/// Citywide figures, taken from the census's `CITY_OF_TORONTO` column.
+#[derive(Debug, Serialize)]
+pub struct CityTotal {
+    pub population: Option<u32>,
+    /// Every counted case, including those without a neighbourhood.
+    pub covid_case_count: u32,
+    pub cases_per_100k: Option<f64>,
+}
+
+pub fn city_total(aggregates: &Aggregates, populations: &HashMap<String, u32>) -> CityTotal {
+    let population = populations.get(CITY_OF_TORONTO).copied();
+    let covid_case_count = aggregates.total;
+    CityTotal {
+        population,
+        covid_case_count,
+        cases_per_100k: population.and_then(|p| rate_per_100k(covid_case_count, p)),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    GeoJson(#[from] GeoJsonError),
+    #[error(transparent)]
+    Census(#[from] CensusError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Joins already tallied COVID data and census values onto the neighbourhood features, ranking
+/// them with `attach_ranks`, bounding them with `attach_bboxes` and attaching each of
+/// `census_fields` under its key. The citywide figures, the cases without a neighbourhood and the
+/// `covid_rate` denominator are added as `city_total`, `unassigned_cases` and `rate_per` members,
+/// and the features are put in `sort_features` order.
+pub fn assemble(
+    geojson: GeoJson,
+    aggregates: &Aggregates,
+    census: &CensusData,
+    census_fields: &[(&str, HashMap<String, f64>)],
+    rate_per: u32,
+    policy: UnknownPolicy,
+    names: &Names,
+) -> Result<(FeatureCollection, Unmatched), Error> {
+    let mut neighbourhoods = into_feature_collection(geojson)?;
+    let unmatched = join(
+        &mut neighbourhoods,
+        &aggregates.per_neighbourhood,
+        census,
+        rate_per,
+        policy,
+        names,
+    );
+    attach_ranks(&mut neighbourhoods);
+    attach_bboxes(&mut neighbourhoods);
+    for (key, values) in census_fields.iter() {
+        attach_values(&mut neighbourhoods, key, values, names);
+    }
+    sort_features(&mut neighbourhoods, names);
+    let city_total = serde_json::to_value(city_total(aggregates, &census.populations))?;
+    let members = neighbourhoods
+        .foreign_members
+        .get_or_insert_with(Default::default);
+    members.insert("city_total".to_owned(), city_total);
+    members.insert("unassigned_cases".to_owned(), aggregates.unassigned.into());
+    members.insert("rate_per".to_owned(), rate_per.into());
+    Ok((neighbourhoods, unmatched))
+}
+
+/// Orders features by their normalized neighbourhood name, so the output doesn't depend on the
+/// order of the input. Features without a readable name go last, in their original order.
+pub fn sort_features(neighbourhoods: &mut FeatureCollection, names: &Names) {
+    neighbourhoods.features.sort_by_cached_key(|feature| {
+        let name = feature
+            .properties
+            .as_ref()
+            .and_then(|p| get_name(p, names).ok())
+            .map(|name| normalize_for_match(&name));
+        (name.is_none(), name)
+    });
+}
+
+/// Finds features that share a neighbourhood name, as `join` would match it, e.g. a neighbourhood
+/// exported as several pieces. With `merge`, the polygons of each later feature are added to the
+/// first's geometry, which becomes a multipolygon; otherwise later features are dropped. Either
+/// way, the first feature's properties are kept. Returns the names that had duplicates.
+pub fn dedup_features(
+    neighbourhoods: &mut FeatureCollection,
+    merge: bool,
+    names: &Names,
+) -> Vec<String> {
+    let mut first = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (i, feature) in neighbourhoods.features.iter().enumerate() {
+        let properties = feature.properties.as_ref();
+        let name = match properties.and_then(|p| matched_name(p, names)) {
+            Some(name) => name,
+            None => continue,
+        };
+        match first.entry(normalize_for_match(&name)) {
+            std::collections::hash_map::Entry::Occupied(kept) => {
+                duplicates.push((i, *kept.get(), name))
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+        }
+    }
+    if duplicates.is_empty() {
+        return Vec::new();
+    }
+
+    let mut duplicated = BTreeSet::new();
+    let mut features = std::mem::take(&mut neighbourhoods.features)
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    for (i, kept, name) in duplicates {
+        duplicated.insert(name);
+        let duplicate = features[i].take();
+        if !merge {
+            continue;
+        }
+        let parts = duplicate
+            .and_then(|f| f.geometry)
+            .and_then(|g| geometry::polygons(&g.value))
+            .unwrap_or_default();
+        let kept = match features[kept].as_mut() {
+            Some(kept) => kept,
+            None => continue,
+        };
+        let mut polygons = kept
+            .geometry
+            .as_ref()
+            .and_then(|g| geometry::polygons(&g.value))
+            .unwrap_or_default();
+        polygons.extend(parts);
+        let multipolygon = geojson::Value::from(&geo::MultiPolygon(polygons));
+        kept.geometry = Some(geojson::Geometry::new(multipolygon));
+    }
+    neighbourhoods.features = features.into_iter().flatten().collect();
+    duplicated.into_iter().collect()
+}
+
+/// A synthetic `CITY_OF_TORONTO` feature rolling up every matched neighbourhood, for selecting the
+/// city as a whole on a map. Its geometry is their polygons dissolved into one by
+/// `geometry::dissolve`, and its properties are the `NeighbourhoodSummary` of their summed cases,
+/// populations and areas. Only the first feature of a neighbourhood with several is used, for its
+/// cases, population, area and polygons alike.
+pub fn city_feature(
+    neighbourhoods: &FeatureCollection,
+    per_neighbourhood: &HashMap<String, CaseCounts>,
+    census: &CensusData,
+    rate_per: u32,
+    policy: UnknownPolicy,
+    names: &Names,
+) -> Feature {
+    let mut counts = CaseCounts::default();
+    let mut population = None;
+    let mut area = None;
+    let mut polygons = Vec::new();
+    let mut seen = HashSet::new();
+    for feature in neighbourhoods.features.iter() {
+        let properties = feature.properties.as_ref();
+        let name = match properties.and_then(|p| matched_name(p, names)) {
+            Some(name) => name,
+            None => continue,
+        };
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+        let geometry = feature.geometry.as_ref();
+        polygons.extend(
+            geometry
+                .and_then(|g| geometry::polygons(&g.value))
+                .into_iter()
+                .flatten(),
+        );
+        if let Some(a) = geometry.and_then(|g| geometry::area_km2(&g.value)) {
+            *area.get_or_insert(0.0) += a;
+        }
+        if let Some(c) = per_neighbourhood.get(&name) {
+            counts.merge(c);
+        }
+        if let Some(&p) = census.populations.get(&name) {
+            *population.get_or_insert(0) += p;
+        }
+    }
+    // the city's age structure is the standard, so its standardized rate is its crude rate
+    let age_standardized = population.and_then(|p| rate_per_100k(counts.total, p));
+    let summary = NeighbourhoodSummary::new(
+        CITY_OF_TORONTO.to_owned(),
+        &counts,
+        population,
+        area,
+        age_standardized,
+        rate_per,
+        policy,
+    );
+    let properties = match serde_json::to_value(summary) {
+        Ok(Value::Object(properties)) => properties,
+        _ => unreachable!("a summary serializes to an object"),
+    };
+    let geometry = geometry::dissolve(polygons)
+        .map(|polygon| geojson::Geometry::new(geojson::Value::from(&polygon)));
+    Feature {
+        bbox: None,
+        geometry,
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    }
+}
+
+/// Keeps only the features of the named neighbourhoods, comparing names after
+/// `Names::canonicalize` and `normalize_for_match`, so any known spelling or capitalization of a
+/// name selects it. Returns every name in `wanted` that selected no feature.
+pub fn retain_neighbourhoods(
+    neighbourhoods: &mut FeatureCollection,
+    wanted: &[String],
+    names: &Names,
+) -> Vec<String> {
+    let normalized = wanted
+        .iter()
+        .map(|name| normalize_for_match(names.canonicalize(name.trim())))
+        .collect::<Vec<_>>();
+    let mut found = vec![false; wanted.len()];
+    neighbourhoods.features.retain(|feature| {
+        let properties = feature.properties.as_ref();
+        let name = match properties.and_then(|p| matched_name(p, names)) {
+            Some(name) => normalize_for_match(&name),
+            None => return false,
+        };
+        let mut keep = false;
+        for (i, _) in normalized.iter().enumerate().filter(|(_, w)| **w == name) {
+            found[i] = true;
+            keep = true;
+        }
+        keep
+    });
+    wanted
+        .iter()
+        .zip(found)
+        .filter(|&(_, found)| !found)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Prepends `prefix` to the name of every property that isn't in `original`, i.e. every one added
+/// since the features were read, so they can be merged with other layers without collisions.
+pub fn prefix_properties(
+    neighbourhoods: &mut FeatureCollection,
+    prefix: &str,
+    original: &BTreeSet<String>,
+) {
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(properties) = &mut feature.properties {
+            *properties = std::mem::take(properties)
+                .into_iter()
+                .map(|(key, value)| {
+                    if original.contains(&key) {
+                        (key, value)
+                    } else {
+                        (format!("{}{}", prefix, key), value)
+                    }
+                })
+                .collect();
+        }
+    }
+}
+
+/// The metrics `--metrics` can choose between, by name, with the properties each one keeps.
+pub const METRICS: [(&str, &[&str]); 10] = [
+    ("count", &["covid_case_count"]),
+    ("rate", &["cases_per_100k", "covid_rate"]),
+    ("rank", &["rate_rank", "rate_percentile"]),
+    ("incidence", &["cumulative_incidence_pct"]),
+    (
+        "age_standardized",
+        &[
+            "cases_age_standardized_per_100k",
+            "age_standardization_fallback",
+        ],
+    ),
+    ("outbreak_share", &["covid_outbreak_share"]),
+    ("cfr", &["covid_cfr"]),
+    ("density", &["population_density"]),
+    ("reporting_lag", &["mean_reporting_lag_days"]),
+    ("growth", &["weekly_growth_pct"]),
+];
+
+/// A selection of `METRICS`, given by name separated by commas, e.g. `rate,cfr`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics(Vec<&'static str>);
+
+impl std::str::FromStr for Metrics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let names = METRICS.iter().map(|(name, _)| *name);
+        s.split(',')
+            .map(|metric| {
+                let metric = metric.trim();
+                names.clone().find(|&name| name == metric).ok_or_else(|| {
+                    format!(
+                        "unknown metric `{}`, expected some of {}",
+                        metric,
+                        names.clone().collect::<Vec<_>>().join(", ")
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Metrics)
+    }
+}
+
+impl Metrics {
+    /// Removes the properties of every metric that isn't selected from every feature. The other
+    /// properties, including the neighbourhood's name and population, are kept.
+    pub fn retain(&self, neighbourhoods: &mut FeatureCollection) {
+        let unwanted = METRICS
+            .iter()
+            .filter(|(name, _)| !self.0.contains(name))
+            .flat_map(|(_, keys)| keys.iter())
+            .collect::<Vec<_>>();
+        let properties = neighbourhoods
+            .features
+            .iter_mut()
+            .filter_map(|f| f.properties.as_mut());
+        for properties in properties {
+            // rebuilt rather than removed from, which would move the last property into the gap
+            *properties = std::mem::take(properties)
+                .into_iter()
+                .filter(|(key, _)| !unwanted.contains(&&key.as_str()))
+                .collect();
+        }
+    }
+}
+
+/// Properties the tool only adds with some options, which a merge shouldn't mistake for ones added
+/// by hand.
+pub const OPTIONAL_PROPERTIES: [&str; 6] = [
+    "covid_case_delta",
+    "covid_rate_delta",
+    "rate_per_100k",
+    "case_ids",
+    "case_ids_truncated",
+    "suppressed",
+];
+
+/// Carries over every property of the features in `existing` that isn't in `known`, e.g. notes
+/// added by hand to an earlier output, onto the feature of the same normalized name. Properties the
+/// feature already has are left as they are. Returns how many properties were carried over.
+pub fn merge_existing(
+    neighbourhoods: &mut FeatureCollection,
+    existing: FeatureCollection,
+    known: &BTreeSet<String>,
+    names: &Names,
+) -> usize {
+    let mut kept = existing
+        .features
+        .into_iter()
+        .filter_map(|feature| {
+            let properties = feature.properties?;
+            let name = normalize_for_match(&matched_name(&properties, names)?);
+            let custom = properties
+                .into_iter()
+                .filter(|(key, _)| !known.contains(key))
+                .collect::<serde_json::Map<_, _>>();
+            Some((name, custom))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut merged = 0;
+    for feature in neighbourhoods.features.iter_mut() {
+        let properties = match &mut feature.properties {
+            Some(properties) => properties,
+            None => continue,
+        };
+        let custom = matched_name(properties, names)
+            .and_then(|name| kept.remove(&normalize_for_match(&name)));
+        for (key, value) in custom.into_iter().flatten() {
+            if !properties.contains_key(&key) {
+                properties.insert(key, value);
+                merged += 1;
+            }
+        }
+    }
+    merged
+}
+
+/// Simplifies every (multi)polygon feature's geometry with `geometry::simplify`, leaving the
+/// properties alone. Other geometries are kept as they are.
+pub fn simplify_geometries(neighbourhoods: &mut FeatureCollection, tolerance: f64) {
+    let geometries = neighbourhoods
+        .features
+        .iter_mut()
+        .filter_map(|f| f.geometry.as_mut());
+    for geometry in geometries {
+        if let Some(simplified) = geometry::simplify(&geometry.value, tolerance) {
+            geometry.value = simplified;
+        }
+    }
+}
+
+/// The `crs` member naming EPSG:3857, in the form GeoJSON used before RFC 7946 dropped it.
+fn web_mercator_crs() -> Value {
+    serde_json::json!({
+        "type": "name",
+        "properties": { "name": "urn:ogc:def:crs:EPSG::3857" },
+    })
+}
+
+/// Projects every feature's geometry, and any bounding boxes, including the `bbox` properties from
+/// `attach_bboxes`, from WGS84 to spherical Mercator (EPSG:3857) with `geometry::web_mercator`, and
+/// says so in a `crs` member.
+pub fn reproject_web_mercator(neighbourhoods: &mut FeatureCollection) {
+    let project_bbox = |bbox: &mut Vec<f64>| {
+        if bbox.len() == 4 {
+            let (min_x, min_y) = geometry::web_mercator(bbox[0], bbox[1]);
+            let (max_x, max_y) = geometry::web_mercator(bbox[2], bbox[3]);
+            *bbox = vec![min_x, min_y, max_x, max_y];
+        }
+    };
+    for feature in neighbourhoods.features.iter_mut() {
+        if let Some(geometry) = &mut feature.geometry {
+            geometry::to_web_mercator(&mut geometry.value);
+        }
+        if let Some(bbox) = &mut feature.bbox {
+            project_bbox(bbox);
+        }
+        let property = feature
+            .properties
+            .as_mut()
+            .and_then(|p| p.get_mut("bbox"))
+            .and_then(|bbox| {
+                let values = bbox.as_array()?.iter().map(Value::as_f64);
+                let values = values.collect::<Option<Vec<_>>>()?;
+                Some((bbox, values))
+            });
+        if let Some((value, mut bbox)) = property {
+            project_bbox(&mut bbox);
+            *value = bbox.into();
+        }
+    }
+    if let Some(bbox) = &mut neighbourhoods.bbox {
+        project_bbox(bbox);
+    }
+    neighbourhoods
+        .foreign_members
+        .get_or_insert_with(Default::default)
+        .insert("crs".to_owned(), web_mercator_crs());
+}
+
+/// Builds the GeoJSON output from fully parsed inputs, without touching the filesystem. Populations
+/// are read from the census row named `population_field`, e.g. `DEFAULT_POPULATION_FIELD`.
+pub fn build_output(
+    covid: &[CovidEntry],
+    census: &[CensusEntryCategory],
+    geojson: GeoJson,
+    population_field: &str,
+    names: &Names,
+) -> Result<FeatureCollection, Error> {
+    let aggregates = aggregate(
+        covid.iter().cloned().map(|mut e| {
+            e.retain_extra(&[]);
+            e
+        }),
+        names,
+    );
+    let census = CensusData::new(census.to_vec(), names, population_field)?;
+    let (neighbourhoods, _) = assemble(
+        geojson,
+        &aggregates,
+        &census,
+        &[],
+        DEFAULT_RATE_PER,
+        UnknownPolicy::default(),
+        names,
+    )?;
+    Ok(neighbourhoods)
+}
+
+/// Every neighbourhood's `NeighbourhoodSummary`, built once so a long-running caller such as a
+/// web service can look neighbourhoods up by name.
+pub struct Dataset {
+    /// Keyed by the `normalize_for_match` form of the neighbourhood's canonical name.
+    summaries: HashMap<String, NeighbourhoodSummary>,
+    /// How the summaries were matched, and so how names are looked up.
+    names: Names,
+}
+
+impl Dataset {
+    /// Joins fully parsed inputs as `build_output` does.
+    pub fn new(
+        covid: &[CovidEntry],
+        census: &[CensusEntryCategory],
+        geojson: GeoJson,
+        population_field: &str,
+        names: Names,
+    ) -> Result<Self, Error> {
+        let neighbourhoods = build_output(covid, census, geojson, population_field, &names)?;
+        Ok(Self::from_features(&neighbourhoods, names))
+    }
+
+    /// Reads the summaries `join` attached back out of the features. Features without one are
+    /// skipped.
+    pub fn from_features(neighbourhoods: &FeatureCollection, names: Names) -> Self {
+        let summaries = neighbourhoods
+            .features
+            .iter()
+            .filter_map(|feature| feature.properties.clone())
+            .filter_map(|properties| {
+                serde_json::from_value::<NeighbourhoodSummary>(Value::Object(properties)).ok()
+            })
+            .map(|summary| (normalize_for_match(&summary.name), summary))
+            .collect();
+        Dataset { summaries, names }
+    }
+
+    /// The summary for a neighbourhood, given any known spelling of its name or one within the
+    /// fuzzy threshold of a known name. `None` if no neighbourhood matches.
+    pub fn neighbourhood_stats(&self, name: &str) -> Option<NeighbourhoodSummary> {
+        let name = self.names.canonicalize(name.trim());
+        self.summaries
+            .get(&normalize_for_match(name))
+            .or_else(|| {
+                let (candidate, _) = self.names.fuzzy_substitute(name)?;
+                self.summaries.get(&normalize_for_match(&candidate))
+            })
+            .cloned()
+    }
+}
+
+/// The denominator used for `covid_rate` unless another is chosen.
+pub const DEFAULT_RATE_PER: u32 = 100_000;
+
+/// Cases per `per` residents, or `None` when there is no population to divide by.
+pub fn rate_per(cases: u32, population: u32, per: u32) -> Option<f64> {
+    if population == 0 {
+        None
+    } else {
+        Some(cases as f64 / population as f64 * per as f64)
+    }
+}
+
+/// Cases per 100,000 residents, or `None` when there is no population to divide by.
+pub fn rate_per_100k(cases: u32, population: u32) -> Option<f64> {
+    rate_per(cases, population, 100_000)
+}
+
+/// Cases as a percentage of the population, or `None` when there is no population to divide by.
+/// Capped at 100%, in case a stale or bad population is smaller than the case count.
+pub fn cumulative_incidence_pct(cases: u32, population: u32) -> Option<f64> {
+    rate_per(cases, population, 100).map(|pct| pct.min(100.0))
+}
+
+/// Fraction of cases that were outbreak associated, or `None` when there are no cases.
+pub fn outbreak_share(outbreak: u32, total: u32) -> Option<f64> {
+    if total == 0 {
+        None
+    } else {
+        Some(outbreak as f64 / total as f64)
+    }
+}
+
+/// The percentage change from the cases of the seven days before the last seven to those of the
+/// last seven, where the days run up to `last`, the latest episode date in the data. `None` if the
+/// data, starting at `first`, doesn't cover both weeks, or if there were no cases the week before.
+pub fn weekly_growth_pct(
+    per_day: &BTreeMap<NaiveDate, u32>,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Option<f64> {
+    let week = chrono::Duration::days(7);
+    let recent_start = last - week + chrono::Duration::days(1);
+    let prior_start = recent_start - week;
+    if first > prior_start {
+        return None;
+    }
+    let cases = |from: NaiveDate, to: NaiveDate| per_day.range(from..to).map(|(_, &c)| c).sum();
+    let prior: u32 = cases(prior_start, recent_start);
+    let recent: u32 = cases(recent_start, last.succ_opt()?);
+    if prior == 0 {
+        return None;
+    }
+    Some((recent as f64 - prior as f64) / prior as f64 * 100.0)
+}
+
+/// The mean days from episode to report, or `None` when no case had a plausible lag.
+pub fn mean_reporting_lag(counts: &CaseCounts) -> Option<f64> {
+    if counts.reporting_lags == 0 {
+        None
+    } else {
+        Some(counts.reporting_lag_days as f64 / counts.reporting_lags as f64)
+    }
+}
+
+/// Fraction of closed cases that were fatal, or `None` when no cases have closed yet.
+pub fn case_fatality_rate(fatal: u32, resolved: u32) -> Option<f64> {
+    let closed = fatal + resolved;
+    if closed == 0 {
+        None
+    } else {
+        Some(fatal as f64 / closed as f64)
+    }
+}
+
+/// Known spelling differences between the datasets, as `(alias, canonical)` pairs. The canonical
+/// spelling is the one used by the COVID data.
+const NEIGHBOURHOOD_ALIASES: &[(&str, &str)] = &[
+    ("Weston-Pellam Park", "Weston-Pelham Park"),
+    ("Briar Hill - Belgravia", "Briar Hill-Belgravia"),
+    (
+        "Cabbagetown-South St.James Town",
+        "Cabbagetown-South St. James Town",
+    ),
+    ("North St.James Town", "North St. James Town"),
+    ("Mimico (includes Humber Bay Shores)", "Mimico"),
+    ("Danforth East York", "Danforth-East York"),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum AliasError {
+    #[error("line {line}: expected \"source,canonical\", got {text:?}")]
+    Syntax { line: usize, text: String },
+}
+
+/// Reads an alias file of `source,canonical` lines. Blank lines are skipped, as is a leading
+/// `source,canonical` header. Either name may be quoted with `"`.
+pub fn parse_aliases(csv: &str) -> Result<Vec<(String, String)>, AliasError> {
+    let unquote = |s: &str| {
+        let s = s.trim();
+        s.strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .map_or_else(|| s.to_owned(), |s| s.replace("\"\"", "\""))
+    };
+    let mut aliases = Vec::new();
+    for (i, text) in csv.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let (source, canonical) = match text.split_once(',') {
+            Some((source, canonical)) => (unquote(source), unquote(canonical)),
+            None => (String::new(), String::new()),
+        };
+        if source.is_empty() || canonical.is_empty() {
+            return Err(AliasError::Syntax {
+                line: i + 1,
+                text: text.to_owned(),
+            });
+        }
+        if i == 0 && source == "source" && canonical == "canonical" {
+            continue;
+        }
+        aliases.push((source, canonical));
+    }
+    Ok(aliases)
+}
+
+/// Every canonical name in `aliases` that isn't one of `NEIGHBOURHOOD_NAMES` in any of its
+/// built-in spellings, sorted.
+pub fn unknown_alias_targets(aliases: &[(String, String)]) -> Vec<String> {
+    let known = NEIGHBOURHOOD_NAMES
+        .iter()
+        .chain(NEIGHBOURHOOD_ALIASES.iter().map(|(_, canonical)| canonical))
+        .copied()
+        .collect::<HashSet<_>>();
+    let unknown = aliases
+        .iter()
+        .map(|(_, canonical)| canonical)
+        .filter(|canonical| !known.contains(canonical.as_str()))
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    unknown.into_iter().collect()
+}
+
+/// How neighbourhood names are reconciled across the datasets: the alias table and the tables
+/// derived from it. Every step that matches names takes one, so callers can hold several side by
+/// side. `Names::default()` has only the built-in aliases.
+#[derive(Debug)]
+pub struct Names {
+    /// Each alias, by its exact spelling, mapped to its canonical name.
+    canonical: HashMap<String, String>,
+    /// Every known name and alias, keyed by `normalize_for_match`, mapped to its canonical name.
+    matchable: HashMap<String, String>,
+    /// Every alias of each canonical name.
+    aliases: HashMap<String, Vec<String>>,
+    /// `NEIGHBOURHOOD_NAMES` in their canonical spelling, without the `CITY_OF_TORONTO`
+    /// pseudo-entry.
+    known: Vec<String>,
+    /// The most edits a feature name may be from a known name to be matched to it, or `None` if
+    /// fuzzy matching is off.
+    fuzzy_threshold: Option<usize>,
+    /// The outcome of `fuzzy_substitute` for every name looked up so far.
+    fuzzy_matches: Mutex<HashMap<String, Option<(String, usize)>>>,
+}
+
+impl Clone for Names {
+    fn clone(&self) -> Self {
+        let fuzzy_matches = self.fuzzy_matches.lock().unwrap_or_else(|e| e.into_inner());
+        Names {
+            canonical: self.canonical.clone(),
+            matchable: self.matchable.clone(),
+            aliases: self.aliases.clone(),
+            known: self.known.clone(),
+            fuzzy_threshold: self.fuzzy_threshold,
+            fuzzy_matches: Mutex::new(fuzzy_matches.clone()),
+        }
+    }
+}
+
+impl Default for Names {
+    fn default() -> Self {
+        Names::with_aliases(Vec::new())
+    }
+}
+
+impl Names {
+    /// The built-in aliases, with `aliases` added on top. Where an alias has the same source as a
+    /// built-in one, it replaces it.
+    pub fn with_aliases(aliases: Vec<(String, String)>) -> Self {
+        let sources = aliases
+            .iter()
+            .map(|(source, _)| source.as_str())
+            .collect::<HashSet<_>>();
+        let mut table = NEIGHBOURHOOD_ALIASES
+            .iter()
+            .filter(|(source, _)| !sources.contains(source))
+            .map(|&(source, canonical)| (source.to_owned(), canonical.to_owned()))
+            .collect::<Vec<_>>();
+        table.extend(aliases);
+
+        let canonical = table.iter().cloned().collect::<HashMap<_, _>>();
+        let mut alias_names = HashMap::<_, Vec<_>>::new();
+        for (alias, name) in table.iter() {
+            alias_names
+                .entry(name.clone())
+                .or_default()
+                .push(alias.clone());
+        }
+        let names = NEIGHBOURHOOD_NAMES.iter().map(|&name| {
+            let canonical = canonical.get(name).map_or(name, String::as_str);
+            (name, canonical)
+        });
+        let matchable = names
+            .chain(table.iter().map(|(a, c)| (a.as_str(), c.as_str())))
+            .map(|(name, canonical)| (normalize_for_match(name), canonical.to_owned()))
+            .collect::<HashMap<_, _>>();
+        let mut names = Names {
+            canonical,
+            matchable,
+            aliases: alias_names,
+            known: Vec::new(),
+            fuzzy_threshold: Some(FUZZY_THRESHOLD),
+            fuzzy_matches: Mutex::new(HashMap::new()),
+        };
+        names.known = NEIGHBOURHOOD_NAMES
+            .iter()
+            .map(|n| names.canonicalize(n).to_owned())
+            .filter(|n| n != CITY_OF_TORONTO)
+            .collect();
+        names
+    }
+
+    /// Sets the maximum edit distance at which feature names are substituted with a known name, or
+    /// turns substitution off with `None`, so only exact (or aliased) names match.
+    pub fn with_fuzzy_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.fuzzy_threshold = threshold;
+        self.fuzzy_matches = Mutex::new(HashMap::new());
+        self
+    }
+
+    /// Maps any known spelling of a neighbourhood to its canonical name, first by the alias table
+    /// and then by comparing `normalize_for_match` forms. Unknown names are returned unchanged.
+    pub fn canonicalize<'a>(&'a self, name: &'a str) -> &'a str {
+        if let Some(canonical) = self.canonical.get(name) {
+            return canonical;
+        }
+        self.matchable
+            .get(&normalize_for_match(name))
+            .map_or(name, String::as_str)
+    }
+
+    /// Every known alternate spelling of a canonical neighbourhood name.
+    pub fn aliases_of(&self, canonical: &str) -> &[String] {
+        self.aliases.get(canonical).map_or(&[], Vec::as_slice)
+    }
+
+    /// `NEIGHBOURHOOD_NAMES` in their canonical spelling, without the `CITY_OF_TORONTO`
+    /// pseudo-entry.
+    pub fn known(&self) -> &[String] {
+        &self.known
+    }
+
+    /// Whether `name` is one of `known`, spelled exactly.
+    pub fn is_known(&self, name: &str) -> bool {
+        self.known.iter().any(|known| known == name)
+    }
+}
+
+/// A loose form of a name for comparison: trimmed, lowercased, with runs of whitespace collapsed
+/// to one space and none after a period, so "St.James" and "St. James" compare equal.
+pub fn normalize_for_match(name: &str) -> String {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    name.to_lowercase().replace(". ", ".")
+}
+
+/// The census reports citywide totals as if they were another neighbourhood with this name. It has
+/// no boundary of its own.
+pub const CITY_OF_TORONTO: &str = "City of Toronto";
+
+pub const NEIGHBOURHOOD_NAMES: [&str; 141] = [
+    "Lambton Baby Point",
+    "Yonge-Eglinton",
+    "Ionview",
+    "Flemingdon Park",
+    "Banbury-Don Mills",
+    "Mount Dennis",
+    "Alderwood",
+    "Clanton Park",
+    "Bay Street Corridor",
+    "Don Valley Village",
+    "Bridle Path-Sunnybrook-York Mills",
+    "Downsview-Roding-CFB",
+    "Clairlea-Birchmount",
+    "North Riverdale",
+    "Mount Pleasant West",
+    "Westminster-Branson",
+    "Eringate-Centennial-West Deane",
+    "Oakridge",
+    "Tam O'Shanter-Sullivan",
+    "South Riverdale",
+    "Birchcliffe-Cliffside",
+    "Palmerston-Little Italy",
+    "Kingsview Village-The Westway",
+    "Morningside",
+    "Oakwood Village",
+    "Runnymede-Bloor West Village",
+    "Princess-Rosethorn",
+    "Kensington-Chinatown",
+    "O'Connor-Parkview",
+    "Agincourt North",
+    "Lawrence Park North",
+    "Dorset Park",
+    "Wychwood",
+    "Yonge-St.Clair",
+    "Kingsway South",
+    "Parkwoods-Donalda",
+    "Rexdale-Kipling",
+    "Church-Yonge Corridor",
+    "Brookhaven-Amesbury",
+    "Bayview Village",
+    "Humber Heights-Westmount",
+    "Bayview Woods-Steeles",
+    "Niagara",
+    "Long Branch",
+    "Leaside-Bennington",
+    "St.Andrew-Windfields",
+    "Corso Italia-Davenport",
+    "Wexford/Maryvale",
+    "Cliffcrest",
+    "Steeles",
+    "Broadview North",
+    "Etobicoke West Mall",
+    "L'Amoreaux",
+    "South Parkdale",
+    "Willowdale East",
+    "Bedford Park-Nortown",
+    "North St. James Town",
+    "Woodbine Corridor",
+    "Playter Estates-Danforth",
+    "Lawrence Park South",
+    "Casa Loma",
+    "Scarborough Village",
+    "Edenbridge-Humber Valley",
+    "Beechborough-Greenbrook",
+    "Pleasant View",
+    "Danforth",
+    "Old East York",
+    "Islington-City Centre West",
+    "Humewood-Cedarvale",
+    "York University Heights",
+    "Taylor-Massey",
+    "Mount Olive-Silverstone-Jamestown",
+    "Roncesvalles",
+    "Trinity-Bellwoods",
+    "Mount Pleasant East",
+    "Humbermede",
+    "Keelesdale-Eglinton West",
+    "Highland Creek",
+    "Thorncliffe Park",
+    "Rosedale-Moore Park",
+    "Junction Area",
+    "Lansing-Westgate",
+    "Regent Park",
+    "Thistletown-Beaumond Heights",
+    "Markland Wood",
+    "Guildwood",
+    "Henry Farm",
+    "Maple Leaf",
+    "Danforth East York",
+    "Woburn",
+    "High Park-Swansea",
+    "Milliken",
+    "Victoria Village",
+    "Yorkdale-Glen Park",
+    "Glenfield-Jane Heights",
+    "City of Toronto",
+    "High Park North",
+    "Waterfront Communities-The Island",
+    "Centennial Scarborough",
+    "The Beaches",
+    "Agincourt South-Malvern West",
+    "West Hill",
+    "Englemount-Lawrence",
+    "Rockcliffe-Smythe",
+    "Dovercourt-Wallace Emerson-Junction",
+    "Stonegate-Queensway",
+    "Bathurst Manor",
+    "Newtonbrook West",
+    "Rustic",
+    "Forest Hill South",
+    "Mimico (includes Humber Bay Shores)",
+    "Woodbine-Lumsden",
+    "Caledonia-Fairbank",
+    "Greenwood-Coxwell",
+    "Annex",
+    "Eglinton East",
+    "Malvern",
+    "Hillcrest Village",
+    "Willowdale West",
+    "Little Portugal",
+    "Black Creek",
+    "Kennedy Park",
+    "New Toronto",
+    "University",
+    "East End-Danforth",
+    "Bendale",
+    "Elms-Old Rexdale",
+    "Blake-Jones",
+    "West Humber-Clairville",
+    "Dufferin Grove",
+    "Briar Hill-Belgravia",
+    "Willowridge-Martingrove-Richview",
+    "Pelmo Park-Humberlea",
+    "Cabbagetown-South St. James Town",
+    "Weston-Pelham Park",
+    "Moss Park",
+    "Forest Hill North",
+    "Weston",
+    "Rouge",
+    "Newtonbrook East",
+    "Humber Summit",
+];
@@ -1,306 +1,1584 @@
-use geojson::GeoJson;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
-
-#[derive(Serialize, Deserialize)]
-struct CovidEntry {
-    /// Unique row identifier for Open Data database
-    #[serde(rename = "_id")]
-    id: u32,
-    /// Outbreak associated cases are associated with outbreaks of COVID-19 in Toronto healthcare
-    /// institutions and healthcare settings (e.g. long-term care homes, retirement homes,
-    /// hospitals, etc.) and other Toronto congregate settings (such as homeless shelters).
-    #[serde(rename = "Outbreak Associated")]
-    outbreak_associated: String, // todo: Enum
-
-    /// Age at time of illness. Age groups (in years): ≤19, 20-29, 30-39, 40-49, 50-59, 60-69,
-    /// 70-79, 80-89, 90+, unknown.
-    #[serde(rename = "Age Group")]
-    age_group: Option<String>, // todo: u32 range
-
-    /// Toronto is divided into 140 geographically distinct neighborhoods that were established to
-    /// help government and community agencies with local planning by providing socio-economic data
-    /// for a meaningful geographic area.
-    #[serde(rename = "Neighbourhood Name")]
-    neighbourhood: Option<String>,
-
-    /// Forward sortation area (i.e. first three characters of postal code) based on the case's
-    /// primary home address.
-    #[serde(rename = "FSA")]
-    fsa: Option<String>,
+mod cache;
+
+use chrono::NaiveDate;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use structopt::StructOpt;
+use toronto_covid::filter::Filter;
+use toronto_covid::output::{
+    centroids, expand_path, feature_rates, replace_atomic, timeseries, top_rates, write_csv,
+    write_long_csv, write_table, Format, RateStats,
+};
+use toronto_covid::stream::{self, InputRecord, JsonArray, JsonLines};
+use toronto_covid::topojson::to_topology;
+use toronto_covid::{age, config, fetch, geometry, gpkg, svg};
+use toronto_covid::{
+    aggregate, assemble, attach_case_ids, attach_deltas, attach_rates, census_values, city_feature,
+    dedup_features, find_feature, get_name, into_feature_collection, join, merge_existing,
+    parse_aliases, parse_census, parse_census_csv, prefix_properties, reproject_web_mercator,
+    retain_neighbourhoods, simplify_geometries, suppress_small_counts, unknown_alias_targets,
+    validate_names, Aggregates, CaseCounts, CensusData, CensusRow, Coverage, CovidEntry,
+    DateWindow, Issue, Metrics, Names, UnknownPolicy, Unmatched, CITY_OF_TORONTO,
+    DEFAULT_POPULATION_FIELD, METRICS, NEIGHBOURHOOD_NAMES, OPTIONAL_PROPERTIES,
+    POPULATION_CATEGORY, POPULATION_TOPIC,
+};
+
+#[derive(StructOpt)]
+struct Cli {
+    // origin: https://open.toronto.ca/dataset/neighbourhoods/
+    /// Neighbourhood boundaries GeoJSON
+    #[structopt(
+        long = "neighbourhoods",
+        default_value = "Neighbourhoods.geojson",
+        parse(from_os_str)
+    )]
+    neighbourhoods: PathBuf,
+    // origin: https://open.toronto.ca/dataset/covid-19-cases-in-toronto/
+    /// COVID-19 cases JSON. May be given more than once to count the cases in several files
+    #[structopt(
+        long = "covid",
+        default_value = "COVID19 cases.json",
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    covid: Vec<PathBuf>,
+    // origin: https://open.toronto.ca/dataset/neighbourhood-profiles/
+    /// Neighbourhood profiles (census) JSON, or the CSV as published if it has a .csv extension
+    #[structopt(
+        long = "census",
+        default_value = "neighbourhood-profiles-2016-csv.json",
+        parse(from_os_str)
+    )]
+    census: PathBuf,
+    /// Where to write the joined output. `{date}` is replaced with the --since/--until window,
+    /// e.g. 2021-03 for a month, and `{format}` with the --format
+    #[structopt(long = "out", default_value = "docs/out.geojson", parse(from_os_str))]
+    out: PathBuf,
+    /// Where to write the case counts per forward sortation area. Takes the same placeholders as
+    /// --out
+    #[structopt(
+        long = "fsa-out",
+        default_value = "docs/fsa_counts.json",
+        parse(from_os_str)
+    )]
+    fsa_out: PathBuf,
+    /// Where to write the case counts per neighbourhood within each forward sortation area. Takes
+    /// the same placeholders as --out
+    #[structopt(
+        long = "crosswalk-out",
+        default_value = "docs/fsa_neighbourhood_crosswalk.json",
+        parse(from_os_str)
+    )]
+    crosswalk_out: PathBuf,
+    /// Output format: geojson, csv, timeseries, topojson, gpkg, svg or long-csv
+    #[structopt(long = "format", default_value = "geojson")]
+    format: Format,
+    /// The colours --format svg shades neighbourhoods between, from the lowest case rate to the
+    /// highest, as comma separated #rrggbb values
+    #[structopt(long = "ramp", default_value = "#ffffcc,#fd8d3c,#800026")]
+    ramp: svg::Ramp,
+    /// Download the latest input datasets from Toronto Open Data before running
+    #[structopt(long = "fetch")]
+    fetch: bool,
+    /// Base URL of the Toronto Open Data CKAN instance used by --fetch
+    #[structopt(
+        long = "fetch-base-url",
+        default_value = "https://ckan0.cf.opendata.inter.prod-toronto.ca"
+    )]
+    fetch_base_url: String,
+    /// How many times --fetch tries each request before giving up, backing off between tries
+    #[structopt(long = "fetch-attempts", default_value = "3")]
+    fetch_attempts: u32,
+    /// How long a single --fetch request may take, in seconds
+    #[structopt(long = "fetch-timeout", default_value = "60")]
+    fetch_timeout: u64,
+    /// Only count cases with an episode date on or after this day (YYYY-MM-DD)
+    #[structopt(long = "since")]
+    since: Option<NaiveDate>,
+    /// Only count cases with an episode date on or before this day (YYYY-MM-DD)
+    #[structopt(long = "until")]
+    until: Option<NaiveDate>,
+    /// Attach this census characteristic to each neighbourhood, e.g. "Seniors (65+ years)"
+    #[structopt(long = "census-field")]
+    census_field: Option<String>,
+    /// Attach a census characteristic under a key of your choosing, as "Characteristic:key".
+    /// May be given more than once
+    #[structopt(long = "census-add", number_of_values = 1)]
+    census_add: Vec<CensusAdd>,
+    /// Also give each neighbourhood a `rate_per_100k` of cases per 100,000 of this census
+    /// characteristic instead of the population, e.g. "Total - Private households by household
+    /// type". Only applies to geojson and topojson
+    #[structopt(long = "denominator-field")]
+    denominator_field: Option<String>,
+    /// The census characteristic to read each neighbourhood's population from, e.g. "Population,
+    /// 2021" for a later census
+    #[structopt(long = "population-field", default_value = "Population, 2016")]
+    population_field: String,
+    /// Leave out probable cases, counting only laboratory-confirmed ones
+    #[structopt(long = "confirmed-only")]
+    confirmed_only: bool,
+    /// Pretty-print JSON output (the default)
+    #[structopt(long = "pretty", conflicts_with = "compact")]
+    pretty: bool,
+    /// Write JSON output without any whitespace
+    #[structopt(long = "compact")]
+    compact: bool,
+    /// The number of residents covid_rate and --top report cases per, e.g. 1000 or 100000
+    #[structopt(
+        long = "per",
+        default_value = "100000",
+        parse(try_from_str = "parse_per")
+    )]
+    per: u32,
+    /// Print the N neighbourhoods with the highest case rate after processing
+    #[structopt(long = "top")]
+    top: Option<usize>,
+    /// Write a point at each neighbourhood's centroid instead of its polygon. Only applies to
+    /// geojson and topojson
+    #[structopt(long = "centroids")]
+    centroids: bool,
+    /// Don't show a progress bar while reading the COVID data
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+    /// Log more detail: -v for progress, -vv for debugging output
+    #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
+    verbose: u8,
+    /// Read the COVID data as one JSON object per line. Implied by a .ndjson or .jsonl extension
+    #[structopt(long = "ndjson")]
+    ndjson: bool,
+    /// An older COVID data snapshot to compare against, adding each neighbourhood's change in
+    /// case count and rate as covid_case_delta and covid_rate_delta
+    #[structopt(long = "diff", parse(from_os_str))]
+    diff: Option<PathBuf>,
+    /// Print the neighbourhood containing a point given as "LAT,LNG", and its case rate. May be
+    /// given more than once
+    #[structopt(long = "locate", number_of_values = 1, allow_hyphen_values = true)]
+    locate: Vec<LatLng>,
+    /// How the breakdowns by age, gender, outcome, outbreak and source treat blank or unrecognized
+    /// values: exclude leaves them out, separate counts them in an unknown bucket of their own and
+    /// include-as-other counts them with the breakdown's other bucket
+    #[structopt(long = "unknown-policy", default_value = "separate")]
+    unknown_policy: UnknownPolicy,
+    /// Only count COVID rows matching an expression, e.g.
+    /// "age_group>=60 && outbreak_associated==Sporadic"
+    #[structopt(long = "where")]
+    filter: Option<Filter>,
+    /// Tally the values of a COVID data column the tool doesn't otherwise read, e.g.
+    /// "Currently Hospitalized", into a property of the same name. May be given more than once
+    #[structopt(long = "covid-field", number_of_values = 1)]
+    covid_field: Vec<String>,
+    /// Skip COVID rows whose _id has already been seen
+    #[structopt(long = "dedup")]
+    dedup: bool,
+    /// Fail if any feature or COVID data neighbourhood goes unmatched
+    #[structopt(long = "strict")]
+    strict: bool,
+    /// Always parse the census instead of reusing populations cached in .cache/
+    #[structopt(long = "no-cache")]
+    no_cache: bool,
+    /// A CSV of extra neighbourhood name aliases, as "source,canonical" lines, that take
+    /// precedence over the built-in ones
+    #[structopt(long = "aliases", parse(from_os_str))]
+    aliases: Option<PathBuf>,
+    /// Check each neighbourhood polygon for unclosed or self-intersecting rings and report the
+    /// offending features, without stopping
+    #[structopt(long = "validate-geometry")]
+    validate_geometry: bool,
+    /// Check that the neighbourhood names in the three inputs all reconcile, print a report and
+    /// exit without writing any output, the census cache included. Every COVID row is scanned for
+    /// its neighbourhood name, so this reads the whole file, though without tallying anything
+    #[structopt(long = "check")]
+    check: bool,
+    /// Print how the figures of this neighbourhood, in any known spelling, were derived, from its
+    /// geojson name to the formula of each metric, and exit without writing any output
+    #[structopt(long = "explain")]
+    explain: Option<String>,
+    /// Hide the case figures of neighbourhoods with fewer than this many cases, marking them
+    /// `suppressed`. Only applies to the feature formats: geojson, topojson, gpkg, svg and
+    /// long-csv
+    #[structopt(long = "suppress-below")]
+    suppress_below: Option<u32>,
+    /// Simplify each neighbourhood polygon with this Douglas-Peucker tolerance, in degrees, to
+    /// shrink the output for web maps. Areas and densities still come from the full polygons
+    #[structopt(
+        long = "simplify",
+        conflicts_with = "centroids",
+        parse(try_from_str = "parse_tolerance")
+    )]
+    simplify: Option<f64>,
+    /// Also write every warning-worthy issue met during the run to this file, as JSON
+    #[structopt(long = "report", parse(from_os_str))]
+    report: Option<PathBuf>,
+    /// Only write the feature of this neighbourhood, in any known spelling. Citywide figures still
+    /// count every neighbourhood. May be given more than once. Only applies to the feature formats:
+    /// geojson, topojson, gpkg, svg and long-csv
+    #[structopt(long = "neighbourhood", number_of_values = 1)]
+    neighbourhood: Vec<String>,
+    /// Only write the properties of these metrics, separated by commas, out of count, rate, rank,
+    /// incidence, age_standardized, outbreak_share, cfr, density, reporting_lag and growth. Only
+    /// applies to geojson and topojson
+    #[structopt(long = "metrics")]
+    metrics: Option<Metrics>,
+    /// Give each feature a `case_ids` array of the `_id`s of the cases counted towards it, for
+    /// linking to the case records. Suppressed neighbourhoods get none. Only applies to geojson
+    /// and topojson
+    #[structopt(long = "include-ids")]
+    include_ids: bool,
+    /// The most case ids to give a feature with --include-ids, keeping the lowest
+    #[structopt(long = "max-ids", default_value = "1000")]
+    max_ids: usize,
+    /// Prepend this to the name of every property the tool adds to a feature, e.g. "covid_", so
+    /// they can't collide with other layers' properties. Only applies to geojson and topojson
+    #[structopt(long = "prefix", default_value = "")]
+    prefix: String,
+    /// Keep the properties of each feature in the existing --out file that the tool doesn't
+    /// generate, such as notes added by hand, refreshing the rest. Only applies to geojson
+    #[structopt(long = "merge-existing", conflicts_with = "centroids")]
+    merge_existing: bool,
+    /// Merge features that share a neighbourhood name, such as pieces of one neighbourhood, into a
+    /// single multipolygon feature. Without this, only the first such feature is kept
+    #[structopt(long = "merge-duplicates")]
+    merge_duplicates: bool,
+    /// Add a "City of Toronto" feature covering every neighbourhood, with their summed counts and
+    /// the rates recomputed from them. Only applies to geojson and topojson
+    #[structopt(long = "include-city-total", conflicts_with = "centroids")]
+    include_city_total: bool,
+    /// The most edits a feature name may be from a known neighbourhood name to be matched to it
+    #[structopt(long = "fuzzy-threshold", default_value = "2")]
+    fuzzy_threshold: usize,
+    /// Only match feature names that are known neighbourhood names or aliases, warning about the
+    /// rest instead of matching them to the closest known name
+    #[structopt(long = "no-fuzzy", conflicts_with = "fuzzy_threshold")]
+    no_fuzzy: bool,
+    /// Leave forward sortation areas that aren't letter-digit-letter, e.g. M5V, out of the FSA
+    /// counts. They are reported either way
+    #[structopt(long = "strict-fsa")]
+    strict_fsa: bool,
+    /// The EPSG code of the coordinate system to write positions in: 4326, WGS84 longitude and
+    /// latitude as in the input, or 3857, spherical Mercator in metres. Only applies to geojson
+    #[structopt(
+        long = "reproject",
+        default_value = "4326",
+        parse(try_from_str = "parse_epsg")
+    )]
+    reproject: u32,
+    /// Also write the size, SHA-256 and parsed record count of every input file read, with the
+    /// tool's version and the time of the run, to this file, as JSON
+    #[structopt(long = "manifest", parse(from_os_str))]
+    manifest: Option<PathBuf>,
+    /// A TOML file of defaults for the other options, keyed by their long names, e.g.
+    /// `format = "csv"` or `covid = ["a.json", "b.json"]`. Options given here win over the file
+    #[structopt(long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Run everything as usual, but print a summary of the output instead of writing any files
+    #[structopt(long = "dry-run", conflicts_with = "fetch")]
+    dry_run: bool,
+    /// Print the known neighbourhood names, sorted, and exit without reading any input
+    #[structopt(long = "list-neighbourhoods")]
+    list_neighbourhoods: bool,
+    /// With --list-neighbourhoods, print the names as a JSON array
+    #[structopt(long = "json", requires = "list_neighbourhoods")]
+    json: bool,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(tag = "Characteristic")]
-enum CensusEntryCategory {
-    #[serde(rename = "Neighbourhood Number")]
-    NeighbourhoodInformation(CensusEntry),
-    #[serde(rename = "Population, 2016")]
-    Population2016(CensusEntry),
-    #[serde(other)]
-    Other,
+fn parse_per(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(0) => Err("the rate denominator must be positive".to_owned()),
+        Ok(per) => Ok(per),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct CensusEntry {
-    #[serde(rename = "_id")]
-    id: u32,
-    #[serde(rename = "Category")]
-    category: String,
-    #[serde(rename = "Topic")]
-    topic: String,
-    #[serde(rename = "Data Source")]
-    data_source: String,
-    #[serde(flatten)]
-    neighbourhoods: HashMap<String, Option<String>>,
+fn parse_epsg(s: &str) -> Result<u32, String> {
+    match s.trim_start_matches("EPSG:").parse() {
+        Ok(epsg @ 4326) | Ok(epsg @ 3857) => Ok(epsg),
+        Ok(epsg) => Err(format!(
+            "can't reproject to EPSG:{}, only 4326 or 3857",
+            epsg
+        )),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
-fn get_name(data: &serde_json::Map<String, serde_json::Value>) -> Result<String, ()> {
-    let name = match data.get("AREA_NAME").ok_or(())? {
-        Value::String(str) => str,
-        _ => return Err(()),
-    };
-    // munge the name to make it match with the covid data
-    let name = name.split(" (").next().ok_or(())?;
-    let name = neighbourhood_names_normalizer(name);
-    Ok(name.to_owned())
+fn parse_tolerance(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(tolerance) if tolerance.is_finite() && tolerance >= 0.0 => Ok(tolerance),
+        Ok(_) => Err("the tolerance must be a non-negative number".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// A point given on the command line as "LAT,LNG".
+struct LatLng {
+    lat: f64,
+    lng: f64,
+}
+
+impl std::str::FromStr for LatLng {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse =
+            |(lat, lng): (&str, &str)| Some((lat.trim().parse().ok()?, lng.trim().parse().ok()?));
+        match s.split_once(',').and_then(parse) {
+            Some((lat, lng)) => Ok(LatLng { lat, lng }),
+            None => Err(format!("expected \"LAT,LNG\", got {:?}", s)),
+        }
+    }
 }
 
+/// A census characteristic to attach to each feature, and the property to store it under.
+struct CensusAdd {
+    characteristic: String,
+    key: String,
+}
+
+impl std::str::FromStr for CensusAdd {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once(':') {
+            Some((characteristic, key)) if !characteristic.trim().is_empty() && !key.is_empty() => {
+                Ok(CensusAdd {
+                    characteristic: characteristic.to_owned(),
+                    key: key.to_owned(),
+                })
+            }
+            _ => Err(format!(
+                "expected \"Characteristic Name:output_key\", got {:?}",
+                s
+            )),
+        }
+    }
+}
+
+/// The short options, by their long names, so a config file setting one can tell whether the
+/// command line gave it.
+const SHORT_OPTIONS: [(char, &str); 2] = [('q', "quiet"), ('v', "verbose")];
+/// The options counted by how often they're given, which a config file sets with a number.
+const COUNTED_OPTIONS: [&str; 1] = ["verbose"];
+
 fn main() -> quicli::prelude::CliResult {
+    let cli = std::env::args_os().collect::<Vec<_>>();
+    let mut args = Cli::from_iter(cli.iter());
+    if let Some(path) = &args.config {
+        let config = std::fs::read_to_string(path).map_err(|e| {
+            quicli::prelude::format_err!("can't read --config {}: {}", path.display(), e)
+        })?;
+        args = Cli::from_iter(config::merge(
+            &config,
+            cli,
+            &SHORT_OPTIONS,
+            &COUNTED_OPTIONS,
+        )?);
+    }
+    let level = match args.verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Warn)
+        .filter_module("toronto_covid", level)
+        .parse_default_env()
+        .init();
+
+    let mut issues = Vec::new();
+    let mut aliases = Vec::new();
+    if let Some(path) = &args.aliases {
+        aliases = parse_aliases(&std::fs::read_to_string(path)?)?;
+        for canonical in unknown_alias_targets(&aliases) {
+            log::warn!(
+                "alias target {:?} from {} is not a known neighbourhood",
+                canonical,
+                path.display()
+            );
+            let detail = format!(
+                "alias target in {} is not a known neighbourhood",
+                path.display()
+            );
+            issues.push(Issue::new("unknown_alias_target", Some(&canonical), detail));
+        }
+    }
+    let names = Names::with_aliases(aliases)
+        .with_fuzzy_threshold(Some(args.fuzzy_threshold).filter(|_| !args.no_fuzzy));
+
+    if args.list_neighbourhoods {
+        let mut names = NEIGHBOURHOOD_NAMES.to_vec();
+        names.sort_unstable();
+        let mut stdout = std::io::stdout().lock();
+        if args.json {
+            serde_json::to_writer(&mut stdout, &names)?;
+            writeln!(stdout)?;
+        } else {
+            for name in names {
+                writeln!(stdout, "{}", name)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if args.suppress_below.is_some() && matches!(args.format, Format::Csv | Format::TimeSeries) {
+        return Err(quicli::prelude::format_err!(
+            "--suppress-below can't be used with --format {}, which is written from the unsuppressed counts",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.simplify.is_some()
+        && matches!(
+            args.format,
+            Format::Csv | Format::TimeSeries | Format::LongCsv
+        )
+    {
+        return Err(quicli::prelude::format_err!(
+            "--simplify can't be used with --format {}, which has no geometry",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.centroids
+        && matches!(
+            args.format,
+            Format::Csv | Format::TimeSeries | Format::GeoPackage | Format::Svg | Format::LongCsv
+        )
+    {
+        return Err(quicli::prelude::format_err!(
+            "--centroids can't be used with --format {}, only with geojson and topojson",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if !args.prefix.is_empty() && !matches!(args.format, Format::GeoJson | Format::TopoJson) {
+        return Err(quicli::prelude::format_err!(
+            "--prefix can't be used with --format {}, which has a fixed set of columns",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.metrics.is_some() && !matches!(args.format, Format::GeoJson | Format::TopoJson) {
+        return Err(quicli::prelude::format_err!(
+            "--metrics can't be used with --format {}, which has a fixed set of columns",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.denominator_field.is_some()
+        && !matches!(args.format, Format::GeoJson | Format::TopoJson)
+    {
+        return Err(quicli::prelude::format_err!(
+            "--denominator-field can't be used with --format {}, which has a fixed set of columns",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.include_city_total && !matches!(args.format, Format::GeoJson | Format::TopoJson) {
+        return Err(quicli::prelude::format_err!(
+            "--include-city-total can't be used with --format {}, which only has neighbourhoods",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.include_ids && !matches!(args.format, Format::GeoJson | Format::TopoJson) {
+        return Err(quicli::prelude::format_err!(
+            "--include-ids can't be used with --format {}, which has a fixed set of columns",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.merge_existing && args.format != Format::GeoJson {
+        return Err(quicli::prelude::format_err!(
+            "--merge-existing can't be used with --format {}, only with geojson",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.reproject != 4326 && args.format != Format::GeoJson {
+        return Err(quicli::prelude::format_err!(
+            "--reproject can't be used with --format {}, only with geojson",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if !args.neighbourhood.is_empty() && matches!(args.format, Format::Csv | Format::TimeSeries) {
+        return Err(quicli::prelude::format_err!(
+            "--neighbourhood can't be used with --format {}, which always lists every neighbourhood",
+            args.format.name()
+        )
+        .into());
+    }
+
+    if args.fetch {
+        if args.covid.len() > 1 {
+            return Err(quicli::prelude::format_err!(
+                "--fetch downloads a single COVID data file, but --covid was given {} times",
+                args.covid.len()
+            )
+            .into());
+        }
+        let downloads = [
+            (&fetch::NEIGHBOURHOODS, &args.neighbourhoods),
+            (&fetch::COVID_CASES, &args.covid[0]),
+            (&fetch::NEIGHBOURHOOD_PROFILES, &args.census),
+        ];
+        let retry = fetch::Retry {
+            attempts: args.fetch_attempts,
+            timeout: std::time::Duration::from_secs(args.fetch_timeout),
+            ..Default::default()
+        };
+        for (dataset, path) in downloads.iter() {
+            if let Err(e) = fetch::fetch(&args.fetch_base_url, dataset, path, &retry) {
+                if path.exists() {
+                    log::warn!(
+                        "failed to fetch {}, using the copy already at {}: {}",
+                        dataset.package,
+                        path.display(),
+                        e
+                    );
+                } else {
+                    return Err(quicli::prelude::format_err!(
+                        "failed to fetch {} and there's no earlier copy at {}: {}",
+                        dataset.package,
+                        path.display(),
+                        e
+                    )
+                    .into());
+                }
+            }
+        }
+    }
+
+    // every input read, for --manifest
+    let mut inputs = Vec::new();
     let neighbourhoods = {
-        // origin: https://open.toronto.ca/dataset/neighbourhoods/
-        let path = std::path::Path::new("Neighbourhoods.geojson");
-        let data = std::fs::read_to_string(path)?;
-        data.parse::<geojson::GeoJson>()?
+        let mut data = String::new();
+        let (mut file, fingerprint) = stream::open_hashed(&args.neighbourhoods)?;
+        file.read_to_string(&mut data)?;
+        let neighbourhoods = data.parse::<geojson::GeoJson>()?;
+        inputs.push(fingerprint.finish(features(&neighbourhoods).len() as u64)?);
+        neighbourhoods
     };
+    if args.validate_geometry {
+        validate_geometry(&neighbourhoods, &names, &mut issues);
+    }
+    let input_properties = property_keys(features(&neighbourhoods));
 
-    let covid_data: Vec<CovidEntry> = {
-        // origin: https://open.toronto.ca/dataset/covid-19-cases-in-toronto/
-        let path = std::path::Path::new("COVID19 cases.json");
-        let file = std::io::BufReader::new(std::fs::File::open(path)?);
-        serde_json::from_reader(file)?
+    // cached census data is keyed by the names the built-in aliases give, and holds the default
+    // population field's populations
+    let use_cache = !args.no_cache
+        && args.aliases.is_none()
+        && args.population_field.trim() == DEFAULT_POPULATION_FIELD;
+    // the cache only holds `CensusData`, so any other census lookup needs the full parse, as does
+    // counting the rows for --manifest
+    let cached = if !use_cache
+        || args.census_field.is_some()
+        || !args.census_add.is_empty()
+        || args.denominator_field.is_some()
+        || args.manifest.is_some()
+    {
+        None
+    } else {
+        cache::load_census(&args.census)
     };
+    let (census, census_fields, denominators) = match cached {
+        Some(cached) => {
+            log::debug!("using cached census data for {}", args.census.display());
+            (cached, Vec::new(), None)
+        }
+        None => {
+            let requested = args
+                .census_field
+                .iter()
+                .map(|characteristic| (characteristic.as_str(), characteristic.trim()))
+                .chain(
+                    args.census_add
+                        .iter()
+                        .map(|add| (add.characteristic.as_str(), add.key.as_str())),
+                )
+                .collect::<Vec<_>>();
+            let mut rows = 0;
+            let wanted = |row: &CensusRow| {
+                rows += 1;
+                age::is_age_row(row.category, row.topic)
+                    || requested
+                        .iter()
+                        .map(|(c, _)| *c)
+                        .chain(args.denominator_field.as_deref())
+                        .any(|c| c.trim() == row.characteristic.trim())
+            };
+            let (mut file, fingerprint) = stream::open_hashed(&args.census)?;
+            let census = if stream::is_csv(&args.census) {
+                parse_census_csv(file, &args.population_field, wanted)?
+            } else {
+                let mut data = String::new();
+                file.read_to_string(&mut data)?;
+                parse_census(&data, &args.population_field, wanted)?
+            };
+            inputs.push(fingerprint.finish(rows)?);
+            let mut census_fields = Vec::new();
+            for &(characteristic, key) in requested.iter() {
+                census_fields.push((key, census_values(&census, characteristic, &names)?));
+            }
+            let denominators = match &args.denominator_field {
+                Some(characteristic) => Some(census_values(&census, characteristic, &names)?),
+                None => None,
+            };
+            let census = CensusData::new(census, &names, &args.population_field)?;
+            if use_cache && !args.dry_run && !args.check {
+                if let Err(e) = cache::store_census(&args.census, &census) {
+                    log::warn!("failed to cache census data: {}", e);
+                }
+            }
+            (census, census_fields, denominators)
+        }
+    };
+    // only the names matter to --check, so it runs before the COVID data is tallied
+    if args.check {
+        let per_neighbourhood = covid_neighbourhoods(&args.covid, &args, &names)?;
+        let mut collection = into_feature_collection(neighbourhoods)?;
+        dedup_features(&mut collection, args.merge_duplicates, &names);
+        let unmatched = join(
+            &mut collection,
+            &per_neighbourhood,
+            &census,
+            args.per,
+            args.unknown_policy,
+            &names,
+        );
+        let unknown_names = validate_names(&collection.features, &names);
+        return check(&unmatched, &unknown_names, &census, &names);
+    }
 
-    let census: Vec<CensusEntryCategory> = {
-        // origin: https://open.toronto.ca/dataset/neighbourhood-profiles/
-        let path = std::path::Path::new("neighbourhood-profiles-2016-csv.json");
-        let file = std::io::BufReader::new(std::fs::File::open(path)?);
-        serde_json::from_reader(file)?
+    let mut aggregates = read_covid(&args.covid, &args, &names, &mut issues, &mut inputs)?;
+    let invalid_fsas = aggregates.invalid_fsas();
+    for (fsa, count) in invalid_fsas.iter() {
+        log::warn!("malformed FSA {:?} on {} COVID row(s)", fsa, count);
+        let detail = format!("malformed FSA {:?} on {} COVID row(s)", fsa, count);
+        issues.push(Issue::new("invalid_fsa", None, detail));
+    }
+    if args.strict_fsa && !invalid_fsas.is_empty() {
+        aggregates.exclude_invalid_fsas();
+        log::info!("left malformed FSAs out of the FSA counts");
+    }
+    let previous = match &args.diff {
+        Some(path) => Some(read_covid(
+            std::slice::from_ref(path),
+            &args,
+            &names,
+            &mut issues,
+            &mut inputs,
+        )?),
+        None => None,
     };
 
-    let populations = census
-        .into_iter()
-        .filter_map(|c| match c {
-            CensusEntryCategory::Population2016(e) => Some(e),
-            _ => None,
-        })
-        .next()
-        .unwrap();
-    let populations = populations
-        .neighbourhoods
-        .into_iter()
-        .filter_map(|(n, pop)| {
-            if let Some(pop) = pop {
-                let n = neighbourhood_names_normalizer(&n).to_owned();
-                pop.replace(",", "")
-                    .parse::<u32>()
-                    .ok()
-                    .and_then(|pop| Some((n, pop)))
-            } else {
-                None
+    let window = DateWindow {
+        since: args.since,
+        until: args.until,
+    };
+    let expand = |path: &PathBuf| match path.to_str() {
+        Some(template) => PathBuf::from(expand_path(template, &window, args.format)),
+        None => path.clone(),
+    };
+    let out = expand(&args.out);
+    let fsa_out = expand(&args.fsa_out);
+    let crosswalk_out = expand(&args.crosswalk_out);
+
+    log::info!(
+        "counted {} COVID cases across {} neighbourhoods",
+        aggregates.total,
+        aggregates.per_neighbourhood.len()
+    );
+    log::debug!(
+        "read populations for {} neighbourhoods",
+        census.populations.len()
+    );
+    log::debug!(
+        "read age bands for {} neighbourhoods",
+        census.age_bands.len()
+    );
+
+    let mut collection = into_feature_collection(neighbourhoods)?;
+    let duplicates = dedup_features(&mut collection, args.merge_duplicates, &names);
+    for name in duplicates.iter() {
+        let detail = if args.merge_duplicates {
+            "several features, merged into one multipolygon"
+        } else {
+            "several features, keeping only the first"
+        };
+        log::warn!("{} has {}", name, detail);
+        issues.push(Issue::new(
+            "duplicate_feature",
+            Some(name),
+            detail.to_owned(),
+        ));
+    }
+    let (mut neighbourhoods, unmatched) = assemble(
+        geojson::GeoJson::FeatureCollection(collection),
+        &aggregates,
+        &census,
+        &census_fields,
+        args.per,
+        args.unknown_policy,
+        &names,
+    )?;
+    if let Some(previous) = &previous {
+        attach_deltas(
+            &mut neighbourhoods,
+            &aggregates.per_neighbourhood,
+            &previous.per_neighbourhood,
+            &census.populations,
+            &names,
+        );
+    }
+    if let Some(denominators) = &denominators {
+        attach_rates(
+            &mut neighbourhoods,
+            &aggregates.per_neighbourhood,
+            denominators,
+            &names,
+        );
+    }
+    if args.include_ids {
+        attach_case_ids(
+            &mut neighbourhoods,
+            &aggregates.per_neighbourhood,
+            args.max_ids,
+            &names,
+        );
+    }
+    if let Some(below) = args.suppress_below {
+        let suppressed = suppress_small_counts(&mut neighbourhoods, below, &args.covid_field);
+        for name in suppressed.iter() {
+            let detail = format!("fewer than {} cases", below);
+            issues.push(Issue::new("suppressed", Some(name), detail));
+        }
+        log::info!(
+            "suppressed the counts of {} neighbourhood(s) with fewer than {} cases",
+            suppressed.len(),
+            below
+        );
+    }
+    if let Some(name) = &args.explain {
+        return explain(
+            name,
+            &neighbourhoods,
+            &aggregates,
+            &unmatched,
+            &args,
+            &names,
+        );
+    }
+    // built before simplifying, so its area comes from the full polygons like the neighbourhoods'
+    let mut city = if args.include_city_total {
+        Some(city_feature(
+            &neighbourhoods,
+            &aggregates.per_neighbourhood,
+            &census,
+            args.per,
+            args.unknown_policy,
+            &names,
+        ))
+    } else {
+        None
+    };
+    if let Some(tolerance) = args.simplify {
+        simplify_geometries(&mut neighbourhoods, tolerance);
+        let geometry = city.as_mut().and_then(|city| city.geometry.as_mut());
+        if let Some(geometry) = geometry {
+            if let Some(simplified) = geometry::simplify(&geometry.value, tolerance) {
+                geometry.value = simplified;
             }
-        })
-        .collect::<HashMap<_, _>>();
-    // println!("{:#?}", populations.keys());
-
-    let mut per_neighbourhood_count = std::collections::HashMap::new();
-    for e in covid_data.iter() {
-        if let Some(neighbourhood) = &e.neighbourhood {
-            *per_neighbourhood_count
-                .entry(neighbourhood_names_normalizer(neighbourhood).to_owned())
-                .or_insert(0u32) += 1;
         }
     }
+    let mut metadata = serde_json::json!({
+        "generated_at": generated_at().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        "cases_per_100k": RateStats::new(feature_rates(&neighbourhoods)),
+    });
+    if let Some(characteristic) = &args.denominator_field {
+        metadata["rate_per_100k_denominator"] = characteristic.trim().into();
+    }
+    neighbourhoods
+        .foreign_members
+        .get_or_insert_with(Default::default)
+        .insert("metadata".to_owned(), metadata);
+    log::debug!(
+        "read {} neighbourhood features",
+        neighbourhoods.features.len()
+    );
+    let unknown_names = validate_names(&neighbourhoods.features, &names);
+    for name in unknown_names.iter() {
+        let detail = "in only one of the geojson and the known names".to_owned();
+        issues.push(Issue::new("unknown_name", Some(name), detail));
+    }
+    if !unknown_names.is_empty() {
+        log::warn!(
+            "{} neighbourhood name(s) not shared between the geojson and the known names: {}",
+            unknown_names.len(),
+            unknown_names.join(", ")
+        );
+    }
 
-    let neighbourhoods = match neighbourhoods {
-        GeoJson::FeatureCollection(mut neighbourhoods) => {
-            for feature in neighbourhoods.features.iter_mut() {
-                if let Some(properties) = &mut feature.properties {
-                    let name = get_name(properties).unwrap();
+    for (name, candidate, distance) in unmatched.fuzzy.iter() {
+        log::info!(
+            "using closest known name {} for {}, {} edit(s) away",
+            candidate,
+            name,
+            distance
+        );
+    }
+    for (id, e) in unmatched.unnamed.iter() {
+        match id {
+            Some(id) => log::warn!("skipping feature {}: {}", id, e),
+            None => log::warn!("skipping feature without an _id: {}", e),
+        }
+    }
+    if !unmatched.no_cases.is_empty() {
+        log::info!(
+            "no COVID cases in {} neighbourhood(s): {}",
+            unmatched.no_cases.len(),
+            unmatched.no_cases.join(", ")
+        );
+    }
+    if !unmatched.covid.is_empty() {
+        log::warn!(
+            "no COVID data for {} neighbourhood(s): {}",
+            unmatched.covid.len(),
+            unmatched.covid.join(", ")
+        );
+    }
+    if !unmatched.census.is_empty() {
+        log::warn!(
+            "no census population for {} neighbourhood(s): {}",
+            unmatched.census.len(),
+            unmatched.census.join(", ")
+        );
+    }
+    for (name, code, number) in unmatched.numbers.iter() {
+        log::warn!(
+            "{} has AREA_SHORT_CODE {} but is neighbourhood number {} in the census",
+            name,
+            code,
+            number
+        );
+    }
+    if !unmatched.features.is_empty() {
+        log::warn!(
+            "no feature for {} COVID data neighbourhood(s): {}",
+            unmatched.features.len(),
+            unmatched.features.join(", ")
+        );
+    }
 
-                    let covid_case_count = *per_neighbourhood_count.get(&name).unwrap();
-                    let v = serde_json::Value::Number(covid_case_count.into());
-                    properties.insert("covid_case_count".to_owned(), v);
+    // --locate still searches every neighbourhood, so only the written copy is narrowed down
+    let focused;
+    let written = if args.neighbourhood.is_empty() {
+        &neighbourhoods
+    } else {
+        let mut focus = neighbourhoods.clone();
+        let missing = retain_neighbourhoods(&mut focus, &args.neighbourhood, &names);
+        if !missing.is_empty() {
+            return Err(quicli::prelude::format_err!(
+                "no feature for --neighbourhood {}",
+                missing.join(", ")
+            )
+            .into());
+        }
+        focused = focus;
+        &focused
+    };
 
-                    let population = *populations.get(&name).unwrap();
-                    let v = serde_json::Value::Number(population.into());
-                    properties.insert("population".to_owned(), v);
-                }
+    let mut output = Output {
+        dry_run: args.dry_run,
+        written: Vec::new(),
+    };
+    let mut features = if args.centroids {
+        Cow::Owned(centroids(written, &names))
+    } else {
+        Cow::Borrowed(written)
+    };
+    if let Some(city) = city {
+        features.to_mut().features.push(city);
+    }
+    if args.reproject == 3857 {
+        reproject_web_mercator(features.to_mut());
+    }
+    if let Some(metrics) = &args.metrics {
+        metrics.retain(features.to_mut());
+    }
+    if !args.prefix.is_empty() {
+        // every property of a centroid is one of ours
+        let original = if args.centroids {
+            BTreeSet::new()
+        } else {
+            input_properties.clone()
+        };
+        prefix_properties(features.to_mut(), &args.prefix, &original);
+    }
+    if args.merge_existing && out.exists() {
+        let existing = std::fs::read_to_string(&out)?.parse::<geojson::GeoJson>()?;
+        let known = property_keys(features.features.iter())
+            .into_iter()
+            .chain(input_properties.iter().cloned())
+            .chain(
+                OPTIONAL_PROPERTIES
+                    .iter()
+                    .chain(METRICS.iter().flat_map(|(_, keys)| keys.iter()))
+                    .map(|key| format!("{}{}", args.prefix, key)),
+            )
+            .collect();
+        let merged = merge_existing(
+            features.to_mut(),
+            into_feature_collection(existing)?,
+            &known,
+            &names,
+        );
+        log::info!("kept {} properties from {}", merged, out.display());
+    }
+    let pretty = args.pretty || !args.compact;
+    match args.format {
+        Format::GeoJson => output.write_json(&out, &*features, pretty)?,
+        Format::Csv => {
+            output.write_atomic(&out, |file| {
+                write_csv(
+                    file,
+                    &aggregates.per_neighbourhood,
+                    &census.populations,
+                    &names,
+                )?;
+                Ok(())
+            })?;
+        }
+        Format::TimeSeries => {
+            output.write_json(&out, &timeseries(&aggregates.per_neighbourhood), pretty)?
+        }
+        Format::TopoJson => output.write_json(&out, &to_topology(&features), pretty)?,
+        Format::GeoPackage => output.write_gpkg(&out, written)?,
+        Format::Svg => output.write_atomic(&out, |file| {
+            svg::write(file, written, &args.ramp)?;
+            Ok(())
+        })?,
+        Format::LongCsv => output.write_atomic(&out, |file| {
+            write_long_csv(file, written)?;
+            Ok(())
+        })?,
+    }
+
+    // sorted so the file is stable between runs
+    let per_fsa_count = aggregates.per_fsa.iter().collect::<BTreeMap<_, _>>();
+    output.write_json(&fsa_out, &per_fsa_count, pretty)?;
+    let crosswalk = aggregates
+        .per_fsa_neighbourhood
+        .iter()
+        .collect::<BTreeMap<_, _>>();
+    output.write_json(&crosswalk_out, &crosswalk, pretty)?;
+    if let Some(path) = &args.manifest {
+        let manifest = serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "generated_at": generated_at().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "inputs": inputs,
+        });
+        output.write_json(path, &manifest, pretty)?;
+    }
+    if !args.dry_run {
+        log::info!("wrote {}", out.display());
+    }
+
+    if let Some(n) = args.top {
+        let rows = top_rates(
+            &aggregates.per_neighbourhood,
+            &census.populations,
+            n,
+            args.per,
+            &names,
+        );
+        write_table(std::io::stdout().lock(), &rows, args.per)?;
+    }
+
+    for point in args.locate.iter() {
+        let feature = geometry::locate(&neighbourhoods, point.lng, point.lat);
+        match feature.and_then(|f| f.properties.as_ref()) {
+            Some(properties) => {
+                let name = properties
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default();
+                let rate = match properties.get("cases_per_100k").and_then(|r| r.as_f64()) {
+                    Some(rate) => format!("{:.1} per 100k", rate),
+                    None if properties.get("suppressed") == Some(&true.into()) => {
+                        "suppressed".to_owned()
+                    }
+                    None => "no population".to_owned(),
+                };
+                println!("{},{}: {} ({})", point.lat, point.lng, name, rate);
             }
-            neighbourhoods
+            None => println!("{},{}: outside Toronto", point.lat, point.lng),
         }
-        _ => unimplemented!(),
+    }
+
+    let coverage = Coverage::new(&aggregates, &unmatched);
+    eprintln!(
+        "{} COVID rows, {} with a neighbourhood, {} dropped as missing or unmatched, {:.1}% matched",
+        coverage.total,
+        coverage.with_neighbourhood,
+        coverage.dropped,
+        coverage.percent_matched()
+    );
+
+    if let Some(path) = &args.report {
+        issues.extend(unmatched.issues());
+        output.write_json(path, &serde_json::json!({ "issues": issues }), pretty)?;
+    }
+
+    if args.dry_run {
+        let added = property_keys(features.features.iter())
+            .difference(&input_properties)
+            .cloned()
+            .collect::<Vec<_>>();
+        println!(
+            "{} feature(s), adding {} propert(ies): {}",
+            features.features.len(),
+            added.len(),
+            added.join(", ")
+        );
+        for (path, size) in output.written.iter() {
+            println!("would write {} ({} bytes)", path.display(), size);
+        }
+    }
+
+    if args.strict {
+        let names = unmatched
+            .unnamed
+            .iter()
+            .map(|(id, _)| {
+                id.as_ref()
+                    .map_or("feature without an _id".to_owned(), |id| {
+                        format!("feature {}", id)
+                    })
+            })
+            .chain(unmatched.covid.iter().cloned())
+            .chain(unmatched.features.iter().cloned())
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            return Err(quicli::prelude::format_err!(
+                "{} neighbourhood(s) went unmatched: {}",
+                names.len(),
+                names.join(", ")
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads and tallies the COVID rows of every file in `paths`, as if they were one, applying the
+/// filters selected on the command line.
+fn read_covid(
+    paths: &[PathBuf],
+    args: &Cli,
+    names: &Names,
+    issues: &mut Vec<Issue>,
+    inputs: &mut Vec<InputRecord>,
+) -> Result<toronto_covid::Aggregates, quicli::prelude::Error> {
+    let window = DateWindow {
+        since: args.since,
+        until: args.until,
     };
+    // driven by the bytes read rather than rows, since the row count isn't known up front
+    let progress = if args.quiet {
+        ProgressBar::hidden()
+    } else {
+        let mut size = 0;
+        for path in paths.iter() {
+            size += std::fs::metadata(path)?.len();
+        }
+        // draws nothing unless stderr is a terminal
+        let bar = ProgressBar::new(size);
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({eta})")
+                .expect("valid progress template"),
+        );
+        bar
+    };
+    let mut files = Vec::with_capacity(paths.len());
+    let mut fingerprints = Vec::with_capacity(paths.len());
+    for path in paths.iter() {
+        let file = progress.wrap_read(std::fs::File::open(path)?);
+        let (file, fingerprint) = stream::hashing(path, file);
+        let file = stream::decode(path, file);
+        let entries: Box<dyn Iterator<Item = serde_json::Result<CovidEntry>> + Send> =
+            if args.ndjson || stream::is_json_lines(path) {
+                Box::new(JsonLines::new(file))
+            } else {
+                Box::new(JsonArray::new(file))
+            };
+        // rows parsed, before any are filtered out
+        let rows = Arc::new(AtomicU64::new(0));
+        let counted = rows.clone();
+        files.push(entries.inspect(move |e| {
+            if e.is_ok() {
+                counted.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+        fingerprints.push((fingerprint, rows));
+    }
+    let mut error = None;
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+    let entries = files
+        .into_iter()
+        .flatten()
+        .map_while(|e| e.map_err(|e| error = Some(e)).ok())
+        .map(|mut e| {
+            e.retain_extra(&args.covid_field);
+            e
+        })
+        .filter(|e| {
+            let repeat = args.dedup && !seen.insert(e.id);
+            duplicates += repeat as u32;
+            !repeat
+        })
+        .filter(|e| window.contains(e.episode_date))
+        .filter(|e| !args.confirmed_only || e.is_confirmed())
+        .filter(|e| args.filter.as_ref().is_none_or(|f| f.matches(e)));
+    let aggregates = aggregate(entries, names);
+    progress.finish_and_clear();
+    if let Some(e) = error {
+        return Err(e.into());
+    }
+    for (fingerprint, rows) in fingerprints {
+        inputs.push(fingerprint.finish(rows.load(Ordering::Relaxed))?);
+    }
+    if duplicates > 0 {
+        let paths = paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>();
+        let detail = format!(
+            "dropped {} duplicate COVID row(s) from {}",
+            duplicates,
+            paths.join(", ")
+        );
+        log::warn!("{}", detail);
+        issues.push(Issue::new("duplicate_rows", None, detail));
+    }
+    Ok(aggregates)
+}
 
-    let out_path = std::path::Path::new("docs").join("out.geojson");
-    std::fs::write(out_path, neighbourhoods.to_string())?;
+/// The neighbourhoods named in the COVID data files in `paths`, each with no cases, for `--check`.
+/// Only the names are read, so none of the filters apply.
+fn covid_neighbourhoods(
+    paths: &[PathBuf],
+    args: &Cli,
+    names: &Names,
+) -> Result<HashMap<String, CaseCounts>, quicli::prelude::Error> {
+    let mut neighbourhoods = HashMap::new();
+    for path in paths.iter() {
+        let file = stream::decode(path, std::fs::File::open(path)?);
+        let entries: Box<dyn Iterator<Item = serde_json::Result<CovidEntry>>> =
+            if args.ndjson || stream::is_json_lines(path) {
+                Box::new(JsonLines::new(file))
+            } else {
+                Box::new(JsonArray::new(file))
+            };
+        for entry in entries {
+            if let Some(name) = entry?.named_neighbourhood() {
+                let name = names.canonicalize(name).to_owned();
+                neighbourhoods
+                    .entry(name)
+                    .or_insert_with(CaseCounts::default);
+            }
+        }
+    }
+    Ok(neighbourhoods)
+}
+
+/// Prints whether each input's neighbourhood names reconciled with the others, failing if any
+/// didn't. Only naming problems fail the check; handled differences are listed as notes.
+fn check(
+    unmatched: &Unmatched,
+    unknown_names: &[String],
+    census: &CensusData,
+    names: &Names,
+) -> quicli::prelude::CliResult {
+    let unnamed = unmatched.unnamed.iter().map(|(id, e)| match id {
+        Some(id) => format!("feature {}: {}", id, e),
+        None => format!("feature without an _id: {}", e),
+    });
+    let mut census_columns = census
+        .populations
+        .keys()
+        .filter(|&name| name != CITY_OF_TORONTO && !names.is_known(name))
+        .cloned()
+        .collect::<Vec<_>>();
+    census_columns.sort_unstable();
+    let checks = [
+        (
+            "geojson feature names",
+            unnamed
+                .chain(unknown_names.iter().map(|name| {
+                    format!("{} is in only one of the geojson and the known names", name)
+                }))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "COVID data neighbourhoods",
+            unmatched
+                .features
+                .iter()
+                .map(|name| format!("{} has no feature", name))
+                .chain(
+                    unmatched
+                        .covid
+                        .iter()
+                        .map(|name| format!("{} has no COVID data", name)),
+                )
+                .collect(),
+        ),
+        (
+            "census neighbourhood columns",
+            census_columns
+                .into_iter()
+                .map(|name| format!("{} is not a known neighbourhood", name))
+                .chain(
+                    unmatched
+                        .census
+                        .iter()
+                        .map(|name| format!("{} has no census population", name)),
+                )
+                .collect(),
+        ),
+    ];
+    let mut stdout = std::io::stdout().lock();
+    for (input, problems) in checks.iter() {
+        let status = if problems.is_empty() { "ok  " } else { "FAIL" };
+        writeln!(stdout, "{} {}", status, input)?;
+        for problem in problems {
+            writeln!(stdout, "       {}", problem)?;
+        }
+    }
+    for (name, candidate, _) in unmatched.fuzzy.iter() {
+        writeln!(stdout, "note matched {} to {} by spelling", name, candidate)?;
+    }
+    for (name, code, number) in unmatched.numbers.iter() {
+        writeln!(
+            stdout,
+            "note {} has AREA_SHORT_CODE {} but is neighbourhood number {} in the census",
+            name, code, number
+        )?;
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|(_, problems)| !problems.is_empty())
+        .count();
+    if failed > 0 {
+        return Err(quicli::prelude::format_err!("{} check(s) failed", failed).into());
+    }
+    Ok(())
+}
+
+/// Prints, for `--explain`, how one neighbourhood's figures were derived: the name it was matched
+/// under, what was counted towards it and the formula behind each metric, with its inputs filled
+/// in.
+fn explain(
+    name: &str,
+    neighbourhoods: &geojson::FeatureCollection,
+    aggregates: &Aggregates,
+    unmatched: &Unmatched,
+    args: &Cli,
+    names: &Names,
+) -> quicli::prelude::CliResult {
+    let feature = find_feature(neighbourhoods, name, names)
+        .ok_or_else(|| quicli::prelude::format_err!("no feature matches {:?}", name))?;
+    let properties = feature
+        .properties
+        .as_ref()
+        .ok_or_else(|| quicli::prelude::format_err!("{:?} has no properties", name))?;
+    let get = |key: &str| properties.get(key).cloned().unwrap_or_default();
+    let normalized = get_name(properties, names)?;
+    let matched = get("name");
+    let matched = matched.as_str().unwrap_or_default();
+    let no_cases = CaseCounts::default();
+    let counts = aggregates
+        .per_neighbourhood
+        .get(matched)
+        .unwrap_or(&no_cases);
+    let population = get("population");
+    let area = feature
+        .geometry
+        .as_ref()
+        .and_then(|g| geometry::area_km2(&g.value));
 
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "geojson AREA_NAME   {}", get("AREA_NAME"))?;
+    writeln!(stdout, "normalized name     {}", normalized)?;
+    match unmatched.fuzzy.iter().find(|(n, _, _)| *n == normalized) {
+        Some((_, candidate, distance)) => writeln!(
+            stdout,
+            "matched name        {} (the closest known name, {} edit(s) away)",
+            candidate, distance
+        )?,
+        None => writeln!(stdout, "matched name        {}", matched)?,
+    }
+    writeln!(
+        stdout,
+        "COVID rows          {} of the {} read ({} had no neighbourhood)",
+        counts.total, aggregates.total, aggregates.unassigned
+    )?;
+    writeln!(
+        stdout,
+        "census population   {} from the {:?} row ({} / {})",
+        population,
+        args.population_field.trim(),
+        POPULATION_CATEGORY,
+        POPULATION_TOPIC
+    )?;
+    match area {
+        Some(area) => writeln!(stdout, "area                {:.3} km²", area)?,
+        None => writeln!(stdout, "area                none")?,
+    }
+
+    let closed = counts.fatal + counts.resolved;
+    let outbreak_denominator = match args.unknown_policy {
+        UnknownPolicy::Exclude => counts.outbreak + counts.sporadic,
+        _ => counts.total,
+    };
+    let metrics = [
+        (
+            "cases_per_100k",
+            format!("{} cases × 100000 / {} residents", counts.total, population),
+        ),
+        (
+            "covid_rate",
+            format!(
+                "{} cases × {} / {} residents",
+                counts.total, args.per, population
+            ),
+        ),
+        (
+            "cumulative_incidence_pct",
+            format!(
+                "min({} cases × 100 / {} residents, 100)",
+                counts.total, population
+            ),
+        ),
+        (
+            "cases_age_standardized_per_100k",
+            if get("age_standardization_fallback") == true {
+                "cases_per_100k, for lack of census age bands".to_owned()
+            } else {
+                "Σ age group rate × city share of residents in the age group".to_owned()
+            },
+        ),
+        (
+            "population_density",
+            match area {
+                Some(area) => format!("{} residents / {:.3} km²", population, area),
+                None => "no polygon area".to_owned(),
+            },
+        ),
+        (
+            "covid_outbreak_share",
+            format!(
+                "{} outbreak cases / {} cases",
+                counts.outbreak, outbreak_denominator
+            ),
+        ),
+        (
+            "covid_cfr",
+            format!("{} fatal / {} fatal or resolved", counts.fatal, closed),
+        ),
+        (
+            "mean_reporting_lag_days",
+            format!(
+                "{} days / {} cases with a plausible lag",
+                counts.reporting_lag_days, counts.reporting_lags
+            ),
+        ),
+        (
+            "weekly_growth_pct",
+            "(last 7 days' cases - the 7 days' before) × 100 / the 7 days' before".to_owned(),
+        ),
+        (
+            "rate_rank",
+            "1 + neighbourhoods with a higher cases_per_100k".to_owned(),
+        ),
+        (
+            "rate_percentile",
+            "share of the other ranked neighbourhoods with a lower cases_per_100k × 100".to_owned(),
+        ),
+    ];
+    writeln!(stdout, "metrics")?;
+    for (key, formula) in metrics.iter() {
+        writeln!(stdout, "  {} = {} = {}", key, formula, get(key))?;
+    }
     Ok(())
 }
 
-fn neighbourhood_names_normalizer(name: &str) -> &str {
-    match name {
-        "Weston-Pellam Park" => "Weston-Pelham Park",
-        "Briar Hill - Belgravia" => "Briar Hill-Belgravia",
-        "Cabbagetown-South St.James Town" => "Cabbagetown-South St. James Town",
-        "North St.James Town" => "North St. James Town",
-        "Mimico (includes Humber Bay Shores)" => "Mimico",
-        "Danforth East York" => "Danforth-East York",
-        _ => name,
+/// The time to record as the output's generation time: `SOURCE_DATE_EPOCH` if it's set, so
+/// repeated runs can produce identical files, or else now.
+fn generated_at() -> chrono::DateTime<chrono::Utc> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .unwrap_or_else(chrono::Utc::now)
+}
+
+/// The features of a GeoJSON input, however it holds them.
+fn features(geojson: &geojson::GeoJson) -> Vec<&geojson::Feature> {
+    match geojson {
+        geojson::GeoJson::FeatureCollection(collection) => collection.features.iter().collect(),
+        geojson::GeoJson::Feature(feature) => vec![feature],
+        geojson::GeoJson::Geometry(_) => Vec::new(),
+    }
+}
+
+/// Every property key set on any of the features.
+fn property_keys<'a, I>(features: I) -> BTreeSet<String>
+where
+    I: IntoIterator<Item = &'a geojson::Feature>,
+{
+    features
+        .into_iter()
+        .filter_map(|f| f.properties.as_ref())
+        .flat_map(|p| p.keys().cloned())
+        .collect()
+}
+
+/// Warns about every feature with an invalid or missing polygon.
+fn validate_geometry(neighbourhoods: &geojson::GeoJson, names: &Names, issues: &mut Vec<Issue>) {
+    let mut invalid = 0;
+    for (i, feature) in features(neighbourhoods).into_iter().enumerate() {
+        let problems = match &feature.geometry {
+            Some(geometry) => geometry::problems(&geometry.value),
+            None => vec![geometry::Problem::NotAPolygon],
+        };
+        if problems.is_empty() {
+            continue;
+        }
+        invalid += 1;
+        let name = feature
+            .properties
+            .as_ref()
+            .and_then(|p| get_name(p, names).ok())
+            .unwrap_or_else(|| format!("feature {}", i));
+        for problem in problems {
+            log::warn!("invalid geometry for {}: {}", name, problem);
+            issues.push(Issue::new(
+                "invalid_geometry",
+                Some(&name),
+                problem.to_string(),
+            ));
+        }
+    }
+    log::info!("{} feature(s) with invalid geometry", invalid);
+}
+
+/// Where the output files go. With --dry-run nothing is written, but everything is serialized just
+/// as it would be, only to be measured.
+struct Output {
+    dry_run: bool,
+    /// Every file written, or that would have been, with its size in bytes.
+    written: Vec<(PathBuf, u64)>,
+}
+
+impl Output {
+    fn write_json<T: serde::Serialize>(
+        &mut self,
+        path: &std::path::Path,
+        value: &T,
+        pretty: bool,
+    ) -> Result<(), quicli::prelude::Error> {
+        self.write_atomic(path, |file| {
+            if pretty {
+                serde_json::to_writer_pretty(file, value)?;
+            } else {
+                serde_json::to_writer(file, value)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Writes a file next to `path` and renames it into place once it's complete, so anything
+    /// serving `path` never sees it half written.
+    fn write_atomic<F>(
+        &mut self,
+        path: &std::path::Path,
+        write: F,
+    ) -> Result<(), quicli::prelude::Error>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), quicli::prelude::Error>,
+    {
+        if self.dry_run {
+            let mut counter = ByteCount(0);
+            write(&mut counter)?;
+            self.written.push((path.to_owned(), counter.0));
+            return Ok(());
+        }
+        replace_atomic::<_, quicli::prelude::Error>(path, |tmp| {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(tmp)?);
+            write(&mut file)?;
+            let file = file.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+            Ok(())
+        })?;
+        self.written
+            .push((path.to_owned(), std::fs::metadata(path)?.len()));
+        Ok(())
+    }
+
+    fn write_gpkg(
+        &mut self,
+        path: &std::path::Path,
+        neighbourhoods: &geojson::FeatureCollection,
+    ) -> Result<(), quicli::prelude::Error> {
+        if self.dry_run {
+            self.written
+                .push((path.to_owned(), gpkg::size(neighbourhoods)?));
+            return Ok(());
+        }
+        replace_atomic::<_, quicli::prelude::Error>(path, |tmp| {
+            // SQLite would open a leftover file from an interrupted run rather than replace it
+            if tmp.exists() {
+                std::fs::remove_file(tmp)?;
+            }
+            gpkg::write(tmp, neighbourhoods)?;
+            Ok(())
+        })?;
+        self.written
+            .push((path.to_owned(), std::fs::metadata(path)?.len()));
+        Ok(())
     }
 }
 
-const NEIGHBOURHOOD_NAMES: [&str; 141] = [
-    "Lambton Baby Point",
-    "Yonge-Eglinton",
-    "Ionview",
-    "Flemingdon Park",
-    "Banbury-Don Mills",
-    "Mount Dennis",
-    "Alderwood",
-    "Clanton Park",
-    "Bay Street Corridor",
-    "Don Valley Village",
-    "Bridle Path-Sunnybrook-York Mills",
-    "Downsview-Roding-CFB",
-    "Clairlea-Birchmount",
-    "North Riverdale",
-    "Mount Pleasant West",
-    "Westminster-Branson",
-    "Eringate-Centennial-West Deane",
-    "Oakridge",
-    "Tam O'Shanter-Sullivan",
-    "South Riverdale",
-    "Birchcliffe-Cliffside",
-    "Palmerston-Little Italy",
-    "Kingsview Village-The Westway",
-    "Morningside",
-    "Oakwood Village",
-    "Runnymede-Bloor West Village",
-    "Princess-Rosethorn",
-    "Kensington-Chinatown",
-    "O'Connor-Parkview",
-    "Agincourt North",
-    "Lawrence Park North",
-    "Dorset Park",
-    "Wychwood",
-    "Yonge-St.Clair",
-    "Kingsway South",
-    "Parkwoods-Donalda",
-    "Rexdale-Kipling",
-    "Church-Yonge Corridor",
-    "Brookhaven-Amesbury",
-    "Bayview Village",
-    "Humber Heights-Westmount",
-    "Bayview Woods-Steeles",
-    "Niagara",
-    "Long Branch",
-    "Leaside-Bennington",
-    "St.Andrew-Windfields",
-    "Corso Italia-Davenport",
-    "Wexford/Maryvale",
-    "Cliffcrest",
-    "Steeles",
-    "Broadview North",
-    "Etobicoke West Mall",
-    "L'Amoreaux",
-    "South Parkdale",
-    "Willowdale East",
-    "Bedford Park-Nortown",
-    "North St. James Town",
-    "Woodbine Corridor",
-    "Playter Estates-Danforth",
-    "Lawrence Park South",
-    "Casa Loma",
-    "Scarborough Village",
-    "Edenbridge-Humber Valley",
-    "Beechborough-Greenbrook",
-    "Pleasant View",
-    "Danforth",
-    "Old East York",
-    "Islington-City Centre West",
-    "Humewood-Cedarvale",
-    "York University Heights",
-    "Taylor-Massey",
-    "Mount Olive-Silverstone-Jamestown",
-    "Roncesvalles",
-    "Trinity-Bellwoods",
-    "Mount Pleasant East",
-    "Humbermede",
-    "Keelesdale-Eglinton West",
-    "Highland Creek",
-    "Thorncliffe Park",
-    "Rosedale-Moore Park",
-    "Junction Area",
-    "Lansing-Westgate",
-    "Regent Park",
-    "Thistletown-Beaumond Heights",
-    "Markland Wood",
-    "Guildwood",
-    "Henry Farm",
-    "Maple Leaf",
-    "Danforth East York",
-    "Woburn",
-    "High Park-Swansea",
-    "Milliken",
-    "Victoria Village",
-    "Yorkdale-Glen Park",
-    "Glenfield-Jane Heights",
-    "City of Toronto",
-    "High Park North",
-    "Waterfront Communities-The Island",
-    "Centennial Scarborough",
-    "The Beaches",
-    "Agincourt South-Malvern West",
-    "West Hill",
-    "Englemount-Lawrence",
-    "Rockcliffe-Smythe",
-    "Dovercourt-Wallace Emerson-Junction",
-    "Stonegate-Queensway",
-    "Bathurst Manor",
-    "Newtonbrook West",
-    "Rustic",
-    "Forest Hill South",
-    "Mimico (includes Humber Bay Shores)",
-    "Woodbine-Lumsden",
-    "Caledonia-Fairbank",
-    "Greenwood-Coxwell",
-    "Annex",
-    "Eglinton East",
-    "Malvern",
-    "Hillcrest Village",
-    "Willowdale West",
-    "Little Portugal",
-    "Black Creek",
-    "Kennedy Park",
-    "New Toronto",
-    "University",
-    "East End-Danforth",
-    "Bendale",
-    "Elms-Old Rexdale",
-    "Blake-Jones",
-    "West Humber-Clairville",
-    "Dufferin Grove",
-    "Briar Hill-Belgravia",
-    "Willowridge-Martingrove-Richview",
-    "Pelmo Park-Humberlea",
-    "Cabbagetown-South St. James Town",
-    "Weston-Pelham Park",
-    "Moss Park",
-    "Forest Hill North",
-    "Weston",
-    "Rouge",
-    "Newtonbrook East",
-    "Humber Summit",
-];
+/// A writer that only counts the bytes written to it.
+struct ByteCount(u64);
+
+impl Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
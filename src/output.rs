@@ -0,0 +1,390 @@
+use crate::{geometry, get_name, rate_per, rate_per_100k, CaseCounts, DateWindow, Names};
+use geojson::{Feature, FeatureCollection};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The shape of the file written to `--out`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    GeoJson,
+    Csv,
+    TimeSeries,
+    TopoJson,
+    GeoPackage,
+    Svg,
+    LongCsv,
+}
+
+impl Format {
+    /// The name the format is chosen by on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::GeoJson => "geojson",
+            Format::Csv => "csv",
+            Format::TimeSeries => "timeseries",
+            Format::TopoJson => "topojson",
+            Format::GeoPackage => "gpkg",
+            Format::Svg => "svg",
+            Format::LongCsv => "long-csv",
+        }
+    }
+}
+
+/// Has `write` create a file next to `path`, then renames it into place once it's complete, so
+/// anything serving `path` never sees it half written and an interrupted write leaves the previous
+/// copy alone. Missing parent directories are created first.
+pub fn replace_atomic<F, E>(path: &Path, write: F) -> Result<(), E>
+where
+    F: FnOnce(&Path) -> Result<(), E>,
+    E: From<std::io::Error>,
+{
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let result = (|| {
+        write(&tmp)?;
+        if let Err(e) = std::fs::rename(&tmp, path) {
+            // Windows won't rename over a file that's open elsewhere, so make room and retry
+            if cfg!(windows) && path.exists() {
+                std::fs::remove_file(path)?;
+                std::fs::rename(&tmp, path)?;
+            } else {
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Fills in an output path template, replacing `{date}` with the date window's `label` and
+/// `{format}` with the format's name.
+pub fn expand_path(template: &str, window: &DateWindow, format: Format) -> String {
+    template
+        .replace("{date}", &window.label())
+        .replace("{format}", format.name())
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "geojson" => Ok(Format::GeoJson),
+            "csv" => Ok(Format::Csv),
+            "timeseries" => Ok(Format::TimeSeries),
+            "topojson" => Ok(Format::TopoJson),
+            "gpkg" => Ok(Format::GeoPackage),
+            "svg" => Ok(Format::Svg),
+            "long-csv" => Ok(Format::LongCsv),
+            _ => Err(format!(
+                "unknown format `{}`, expected geojson, csv, timeseries, topojson, gpkg, svg or long-csv",
+                s
+            )),
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a delimiter, a quote or a line break.
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Writes one row per known neighbourhood, sorted by name. Missing values are left empty.
+pub fn write_csv<W: Write>(
+    mut w: W,
+    per_neighbourhood_count: &HashMap<String, CaseCounts>,
+    populations: &HashMap<String, u32>,
+    names: &Names,
+) -> std::io::Result<()> {
+    let names = names
+        .known()
+        .iter()
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>();
+
+    writeln!(
+        w,
+        "neighbourhood,population,covid_case_count,cases_per_100k"
+    )?;
+    for name in names {
+        let cases = per_neighbourhood_count.get(name).map_or(0, |c| c.total);
+        let population = populations.get(name).copied();
+        let rate = population.and_then(|p| rate_per_100k(cases, p));
+        writeln!(
+            w,
+            "{},{},{},{}",
+            csv_field(name),
+            population.map(|p| p.to_string()).unwrap_or_default(),
+            cases,
+            rate.map(|r| r.to_string()).unwrap_or_default(),
+        )?;
+    }
+    Ok(())
+}
+
+/// The properties written as metrics by `write_long_csv`, in the order they're written.
+pub const LONG_CSV_METRICS: [&str; 11] = [
+    "cases_per_100k",
+    "covid_active",
+    "covid_case_count",
+    "covid_cfr",
+    "covid_fatal",
+    "covid_outbreak_count",
+    "covid_outbreak_share",
+    "covid_rate",
+    "covid_resolved",
+    "population",
+    "population_density",
+];
+
+/// Writes the joined features as tidy data, one `neighbourhood,metric,value` row per feature and
+/// each of `LONG_CSV_METRICS`, sorted by neighbourhood and then metric. Missing values, including
+/// suppressed ones, are left empty. Features without a name are left out.
+pub fn write_long_csv<W: Write>(
+    mut w: W,
+    neighbourhoods: &FeatureCollection,
+) -> std::io::Result<()> {
+    let rows = neighbourhoods
+        .features
+        .iter()
+        .filter_map(|feature| {
+            let properties = feature.properties.as_ref()?;
+            let name = properties.get("name")?.as_str()?;
+            Some((name, properties))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    writeln!(w, "neighbourhood,metric,value")?;
+    for (name, properties) in rows {
+        for metric in LONG_CSV_METRICS.iter() {
+            let value = match properties.get(*metric) {
+                Some(serde_json::Value::Number(n)) => n.to_string(),
+                _ => String::new(),
+            };
+            writeln!(w, "{},{},{}", csv_field(name), metric, value)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MonthCount {
+    /// Formatted as `YYYY-MM`.
+    pub month: String,
+    pub count: u32,
+}
+
+/// Monthly case counts per neighbourhood, by episode date. Every neighbourhood lists every month
+/// between the earliest and latest observed episode, including months without cases.
+pub fn timeseries(
+    per_neighbourhood_count: &HashMap<String, CaseCounts>,
+) -> BTreeMap<String, Vec<MonthCount>> {
+    let observed = per_neighbourhood_count
+        .values()
+        .flat_map(|c| c.per_month.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    let months = match (observed.iter().next(), observed.iter().next_back()) {
+        (Some(&first), Some(&last)) => month_range(first, last),
+        _ => Vec::new(),
+    };
+
+    per_neighbourhood_count
+        .iter()
+        .filter(|(_, counts)| !counts.per_month.is_empty())
+        .map(|(name, counts)| {
+            let series = months
+                .iter()
+                .map(|&(year, month)| MonthCount {
+                    month: format!("{:04}-{:02}", year, month),
+                    count: counts.per_month.get(&(year, month)).copied().unwrap_or(0),
+                })
+                .collect();
+            (name.clone(), series)
+        })
+        .collect()
+}
+
+fn month_range(first: (i32, u32), last: (i32, u32)) -> Vec<(i32, u32)> {
+    let mut months = Vec::new();
+    let (mut year, mut month) = first;
+    while (year, month) <= last {
+        months.push((year, month));
+        if month == 12 {
+            year += 1;
+            month = 1;
+        } else {
+            month += 1;
+        }
+    }
+    months
+}
+
+/// A row of the `--top` report.
+pub struct RateRow<'a> {
+    pub name: &'a str,
+    pub cases: u32,
+    pub population: u32,
+    pub rate: f64,
+}
+
+/// The `n` known neighbourhoods with the highest rate per `per` residents, highest first. Ties are
+/// broken alphabetically so the order is deterministic.
+pub fn top_rates<'a>(
+    per_neighbourhood_count: &HashMap<String, CaseCounts>,
+    populations: &HashMap<String, u32>,
+    n: usize,
+    per: u32,
+    names: &'a Names,
+) -> Vec<RateRow<'a>> {
+    let mut rows = names
+        .known()
+        .iter()
+        .filter_map(|name| {
+            let cases = per_neighbourhood_count.get(name).map_or(0, |c| c.total);
+            let population = *populations.get(name)?;
+            let rate = rate_per(cases, population, per)?;
+            Some(RateRow {
+                name,
+                cases,
+                population,
+                rate,
+            })
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| b.rate.total_cmp(&a.rate).then_with(|| a.name.cmp(b.name)));
+    rows.truncate(n);
+    rows
+}
+
+/// The spread of a set of rates, for picking a colour scale.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RateStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// The middle rate, or the mean of the middle two when there's an even number.
+    pub median: f64,
+}
+
+impl RateStats {
+    /// `None` when there are no rates.
+    pub fn new(mut rates: Vec<f64>) -> Option<Self> {
+        if rates.is_empty() {
+            return None;
+        }
+        rates.sort_by(f64::total_cmp);
+        let n = rates.len();
+        let median = if n.is_multiple_of(2) {
+            (rates[n / 2 - 1] + rates[n / 2]) / 2.0
+        } else {
+            rates[n / 2]
+        };
+        Some(RateStats {
+            min: rates[0],
+            max: rates[n - 1],
+            mean: rates.iter().sum::<f64>() / n as f64,
+            median,
+        })
+    }
+}
+
+/// The `cases_per_100k` of every feature that has one, i.e. every neighbourhood with a population.
+pub fn feature_rates(neighbourhoods: &FeatureCollection) -> Vec<f64> {
+    neighbourhoods
+        .features
+        .iter()
+        .filter_map(|f| f.properties.as_ref()?.get("cases_per_100k")?.as_f64())
+        .collect()
+}
+
+/// A short name for a rate denominator, e.g. "per 100k".
+pub fn per_label(per: u32) -> String {
+    if per >= 1000 && per.is_multiple_of(1000) {
+        format!("per {}k", per / 1000)
+    } else {
+        format!("per {}", per)
+    }
+}
+
+/// Prints rows as a column-aligned table, with rates per `per` residents.
+pub fn write_table<W: Write>(mut w: W, rows: &[RateRow], per: u32) -> std::io::Result<()> {
+    let width = rows
+        .iter()
+        .map(|r| r.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("neighbourhood".len());
+    writeln!(
+        w,
+        "{:>4}  {:<width$}  {:>6}  {:>10}  {:>10}",
+        "rank",
+        "neighbourhood",
+        "cases",
+        "population",
+        per_label(per),
+        width = width
+    )?;
+    for (i, r) in rows.iter().enumerate() {
+        writeln!(
+            w,
+            "{:>4}  {:<width$}  {:>6}  {:>10}  {:>10.1}",
+            i + 1,
+            r.name,
+            r.cases,
+            r.population,
+            r.rate,
+            width = width
+        )?;
+    }
+    Ok(())
+}
+
+/// A point at the centroid of each neighbourhood, for placing labels. Each point carries the
+/// neighbourhood's `name` along with the `covid_case_count` and `population` already joined onto
+/// it. Features without a polygon are left out.
+pub fn centroids(neighbourhoods: &FeatureCollection, names: &Names) -> FeatureCollection {
+    let features = neighbourhoods
+        .features
+        .iter()
+        .filter_map(|feature| {
+            let source = feature.properties.as_ref()?;
+            let (x, y) = geometry::centroid(&feature.geometry.as_ref()?.value)?;
+
+            let mut properties = serde_json::Map::new();
+            let name = get_name(source, names).ok()?;
+            properties.insert("name".to_owned(), name.into());
+            for key in ["covid_case_count", "population"].iter() {
+                let v = source.get(*key).cloned().unwrap_or(serde_json::Value::Null);
+                properties.insert((*key).to_owned(), v);
+            }
+
+            Some(Feature {
+                bbox: None,
+                geometry: Some(geojson::Geometry::new(geojson::Value::Point(vec![x, y]))),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            })
+        })
+        .collect();
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
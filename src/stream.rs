@@ -0,0 +1,262 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Opens an input file for buffered reading, decompressing it on the fly if its extension is
+/// `.gz`.
+pub fn open(path: &Path) -> std::io::Result<Box<dyn BufRead + Send>> {
+    Ok(decode(path, File::open(path)?))
+}
+
+/// Buffers an already opened input file, decompressing it if `path` ends in `.gz`.
+pub fn decode<R>(path: &Path, file: R) -> Box<dyn BufRead + Send>
+where
+    R: Read + Send + 'static,
+{
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(BufReader::new(flate2::read::GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    }
+}
+
+/// What's known of an input file's provenance: its size and SHA-256, and how many records were
+/// parsed from it.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputRecord {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+    /// Rows for the COVID data and census, features for the neighbourhoods.
+    pub records: u64,
+}
+
+#[derive(Default)]
+struct Hashed {
+    sha256: Sha256,
+    bytes: u64,
+}
+
+/// Passes reads through, hashing the bytes on the way, so an input is fingerprinted by the same pass
+/// that parses it.
+pub struct Hashing<R> {
+    inner: R,
+    hashed: Arc<Mutex<Hashed>>,
+}
+
+impl<R: Read> Read for Hashing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let mut hashed = self.hashed.lock().unwrap_or_else(|e| e.into_inner());
+        hashed.sha256.update(&buf[..n]);
+        hashed.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// The hash of a file being read through a `Hashing` reader, completed by `finish` once parsing is
+/// done.
+pub struct Fingerprint {
+    path: PathBuf,
+    hashed: Arc<Mutex<Hashed>>,
+}
+
+impl Fingerprint {
+    /// Hashes whatever of the file the parser left unread, usually nothing or trailing whitespace,
+    /// and records the whole file's size and hash along with `records`.
+    pub fn finish(self, records: u64) -> std::io::Result<InputRecord> {
+        let mut hashed = self.hashed.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rest = File::open(&self.path)?;
+        rest.seek(SeekFrom::Start(hashed.bytes))?;
+        hashed.bytes += std::io::copy(&mut rest, &mut hashed.sha256)?;
+        let sha256 = std::mem::take(&mut hashed.sha256).finalize();
+        Ok(InputRecord {
+            path: self.path,
+            bytes: hashed.bytes,
+            sha256: sha256.iter().map(|b| format!("{:02x}", b)).collect(),
+            records,
+        })
+    }
+}
+
+/// Wraps the file at `path`, as opened, in a `Hashing` reader. Any decompression has to go on top,
+/// so the hash is of the file as it is on disk.
+pub fn hashing<R: Read>(path: &Path, file: R) -> (Hashing<R>, Fingerprint) {
+    let hashed = Arc::new(Mutex::new(Hashed::default()));
+    let fingerprint = Fingerprint {
+        path: path.to_owned(),
+        hashed: hashed.clone(),
+    };
+    (
+        Hashing {
+            inner: file,
+            hashed,
+        },
+        fingerprint,
+    )
+}
+
+/// Like `open`, fingerprinting the file as it's read.
+pub fn open_hashed(path: &Path) -> std::io::Result<(Box<dyn BufRead + Send>, Fingerprint)> {
+    let (file, fingerprint) = hashing(path, File::open(path)?);
+    Ok((decode(path, file), fingerprint))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Start,
+    First,
+    Rest,
+    Done,
+}
+
+/// Deserializes the elements of a top-level JSON array one at a time, so the array never has to be
+/// held in memory as a whole.
+pub struct JsonArray<R, T> {
+    reader: R,
+    state: State,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: BufRead, T: DeserializeOwned> JsonArray<R, T> {
+    pub fn new(reader: R) -> Self {
+        JsonArray {
+            reader,
+            state: State::Start,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consumes whitespace and returns the next byte without consuming it.
+    fn peek(&mut self) -> std::io::Result<Option<u8>> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(i) => {
+                    let b = buf[i];
+                    self.reader.consume(i);
+                    return Ok(Some(b));
+                }
+                None if buf.is_empty() => return Ok(None),
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: &[u8]) -> serde_json::Result<u8> {
+        match self.peek().map_err(serde_json::Error::io)? {
+            Some(b) if expected.contains(&b) => {
+                self.reader.consume(1);
+                Ok(b)
+            }
+            Some(b) => Err(serde::de::Error::custom(format!(
+                "expected one of {:?}, found {:?}",
+                String::from_utf8_lossy(expected),
+                b as char
+            ))),
+            None => Err(serde::de::Error::custom("unexpected end of JSON array")),
+        }
+    }
+
+    fn next_element(&mut self) -> serde_json::Result<Option<T>> {
+        match self.state {
+            State::Start => {
+                self.expect(b"[")?;
+                self.state = State::First;
+                return self.next_element();
+            }
+            State::First => {
+                if self.peek().map_err(serde_json::Error::io)? == Some(b']') {
+                    self.reader.consume(1);
+                    self.state = State::Done;
+                    return Ok(None);
+                }
+            }
+            State::Rest => {
+                if self.expect(b",]")? == b']' {
+                    self.state = State::Done;
+                    return Ok(None);
+                }
+            }
+            State::Done => return Ok(None),
+        }
+        self.state = State::Rest;
+        let mut de = serde_json::Deserializer::from_reader(&mut self.reader);
+        T::deserialize(&mut de).map(Some)
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for JsonArray<R, T> {
+    type Item = serde_json::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.next_element();
+        if element.is_err() {
+            self.state = State::Done;
+        }
+        element.transpose()
+    }
+}
+
+/// Deserializes newline-delimited JSON, one value per line. Blank lines are skipped.
+pub struct JsonLines<R, T> {
+    lines: std::io::Lines<R>,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: BufRead, T: DeserializeOwned> JsonLines<R, T> {
+    pub fn new(reader: R) -> Self {
+        JsonLines {
+            lines: reader.lines(),
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for JsonLines<R, T> {
+    type Item = serde_json::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let element = loop {
+            match self.lines.next()? {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => break serde_json::from_str(&line),
+                Err(e) => break Err(serde_json::Error::io(e)),
+            }
+        };
+        self.done = element.is_err();
+        Some(element)
+    }
+}
+
+/// The extension of a path, looking past any `.gz`.
+fn extension(path: &Path) -> Option<&std::ffi::OsStr> {
+    let path = match path.extension() {
+        Some(ext) if ext == "gz" => path.file_stem().map_or(path, Path::new),
+        _ => path,
+    };
+    path.extension()
+}
+
+/// Whether a path names newline-delimited JSON by its extension, looking past any `.gz`.
+pub fn is_json_lines(path: &Path) -> bool {
+    extension(path).is_some_and(|ext| ext == "ndjson" || ext == "jsonl")
+}
+
+/// Whether a path names a CSV file by its extension, looking past any `.gz`.
+pub fn is_csv(path: &Path) -> bool {
+    extension(path).is_some_and(|ext| ext == "csv")
+}
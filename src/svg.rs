@@ -0,0 +1,233 @@
+//! A static choropleth of the neighbourhoods as SVG, for a quick look at the output without a web
+//! map. Polygons are drawn in Web Mercator and filled by their `cases_per_100k`.
+
+use crate::output::RateStats;
+use geojson::FeatureCollection;
+use std::io::Write;
+
+/// The width of the drawn map, in SVG user units. The height follows from the map's extent.
+const WIDTH: f64 = 800.0;
+const MARGIN: f64 = 10.0;
+const LEGEND_HEIGHT: f64 = 50.0;
+/// The fill of neighbourhoods without a rate.
+const NO_DATA: &str = "#cccccc";
+
+/// The colours rates are interpolated between, from the lowest rate to the highest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ramp(Vec<[u8; 3]>);
+
+impl std::str::FromStr for Ramp {
+    type Err = String;
+
+    /// Parses colours given as `#rrggbb`, separated by commas.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |colour: &str| {
+            let hex = colour.trim().strip_prefix('#')?;
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            Some([channel(0)?, channel(2)?, channel(4)?])
+        };
+        let colours = s
+            .split(',')
+            .map(|colour| {
+                parse(colour).ok_or_else(|| format!("expected #rrggbb, got {:?}", colour))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if colours.len() < 2 {
+            return Err("a colour ramp needs at least two colours".to_owned());
+        }
+        Ok(Ramp(colours))
+    }
+}
+
+impl Ramp {
+    /// The colour a fraction `t` of the way along the ramp, clamped to its ends.
+    pub fn colour(&self, t: f64) -> String {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let segments = self.0.len() - 1;
+        let position = t * segments as f64;
+        let i = (position.floor() as usize).min(segments - 1);
+        let (from, to) = (self.0[i], self.0[i + 1]);
+        let f = position - i as f64;
+        let mix = |c: usize| (from[c] as f64 + (to[c] as f64 - from[c] as f64) * f).round() as u8;
+        format!("#{:02x}{:02x}{:02x}", mix(0), mix(1), mix(2))
+    }
+}
+
+/// Projects a WGS84 position to Web Mercator, in radians, with y increasing northward.
+fn mercator(position: &[f64]) -> (f64, f64) {
+    let (lng, lat) = (position[0].to_radians(), position[1].to_radians());
+    (lng, (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln())
+}
+
+fn rings(value: &geojson::Value) -> Vec<&Vec<Vec<f64>>> {
+    match value {
+        geojson::Value::Polygon(rings) => rings.iter().collect(),
+        geojson::Value::MultiPolygon(polygons) => polygons.iter().flatten().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Escapes text for use in SVG content or attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes every (multi)polygon feature as a `<path>` filled by where its `cases_per_100k` falls
+/// between the lowest and highest rate, with a legend below the map. Each path has a `<title>`
+/// with the neighbourhood's name and rate.
+pub fn write<W: Write>(
+    mut w: W,
+    neighbourhoods: &FeatureCollection,
+    ramp: &Ramp,
+) -> std::io::Result<()> {
+    let features = neighbourhoods
+        .features
+        .iter()
+        .filter_map(|feature| {
+            let rings = rings(&feature.geometry.as_ref()?.value);
+            let rings = rings
+                .into_iter()
+                .map(|ring| {
+                    ring.iter()
+                        .filter(|p| p.len() >= 2)
+                        .map(|p| mercator(p))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|ring| ring.len() >= 3)
+                .collect::<Vec<_>>();
+            if rings.is_empty() {
+                return None;
+            }
+            let properties = feature.properties.as_ref();
+            let name = properties
+                .and_then(|p| p.get("name")?.as_str())
+                .unwrap_or_default();
+            let rate = properties.and_then(|p| p.get("cases_per_100k")?.as_f64());
+            Some((name, rate, rings))
+        })
+        .collect::<Vec<_>>();
+
+    let points = || {
+        features
+            .iter()
+            .flat_map(|(_, _, rings)| rings.iter().flatten())
+    };
+    let (min_x, max_x) = points().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+        (lo.min(p.0), hi.max(p.0))
+    });
+    let (min_y, max_y) = points().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+        (lo.min(p.1), hi.max(p.1))
+    });
+    let scale = if max_x > min_x {
+        WIDTH / (max_x - min_x)
+    } else {
+        1.0
+    };
+    let map_height = if max_y > min_y {
+        (max_y - min_y) * scale
+    } else {
+        0.0
+    };
+    let project =
+        |&(x, y): &(f64, f64)| (MARGIN + (x - min_x) * scale, MARGIN + (max_y - y) * scale);
+
+    let stats = RateStats::new(features.iter().filter_map(|(_, rate, _)| *rate).collect());
+    let fill = |rate: Option<f64>| match (rate, stats) {
+        (Some(rate), Some(stats)) if stats.max > stats.min => {
+            ramp.colour((rate - stats.min) / (stats.max - stats.min))
+        }
+        (Some(_), Some(_)) => ramp.colour(0.5),
+        _ => NO_DATA.to_owned(),
+    };
+
+    let width = WIDTH + 2.0 * MARGIN;
+    let height = map_height + LEGEND_HEIGHT + 2.0 * MARGIN;
+    writeln!(
+        w,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">"#,
+        width, height, width, height
+    )?;
+    writeln!(w, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    writeln!(w, r##"<g stroke="#ffffff" stroke-width="0.5">"##)?;
+    for (name, rate, rings) in features.iter() {
+        write!(w, r#"<path fill="{}" fill-rule="evenodd" d=""#, fill(*rate))?;
+        for ring in rings.iter() {
+            for (i, point) in ring.iter().enumerate() {
+                let (x, y) = project(point);
+                let command = if i == 0 { 'M' } else { 'L' };
+                write!(w, "{}{:.1},{:.1}", command, x, y)?;
+            }
+            write!(w, "Z")?;
+        }
+        let rate = rate.map_or("no data".to_owned(), |r| format!("{:.1} per 100k", r));
+        writeln!(w, r#""><title>{}: {}</title></path>"#, escape(name), rate)?;
+    }
+    writeln!(w, "</g>")?;
+
+    // the legend: the ramp as a gradient from the lowest rate to the highest, and a swatch for
+    // neighbourhoods without one
+    let top = MARGIN + map_height + 15.0;
+    let bar = WIDTH / 2.0;
+    writeln!(
+        w,
+        r#"<defs><linearGradient id="ramp" x1="0" x2="1" y1="0" y2="0">"#
+    )?;
+    let stops = ramp.0.len() - 1;
+    for i in 0..=stops {
+        let t = i as f64 / stops as f64;
+        writeln!(
+            w,
+            r#"<stop offset="{:.3}" stop-color="{}"/>"#,
+            t,
+            ramp.colour(t)
+        )?;
+    }
+    writeln!(w, "</linearGradient></defs>")?;
+    writeln!(
+        w,
+        r##"<g font-family="sans-serif" font-size="11" fill="#333333">"##
+    )?;
+    writeln!(
+        w,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="12" fill="url(#ramp)"/>"#,
+        MARGIN, top, bar
+    )?;
+    if let Some(stats) = stats {
+        writeln!(
+            w,
+            r#"<text x="{:.1}" y="{:.1}">{:.0}</text>"#,
+            MARGIN,
+            top + 26.0,
+            stats.min
+        )?;
+        writeln!(
+            w,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{:.0} cases per 100k</text>"#,
+            MARGIN + bar,
+            top + 26.0,
+            stats.max
+        )?;
+    }
+    writeln!(
+        w,
+        r#"<rect x="{:.1}" y="{:.1}" width="12" height="12" fill="{}"/>"#,
+        MARGIN + bar + 20.0,
+        top,
+        NO_DATA
+    )?;
+    writeln!(
+        w,
+        r#"<text x="{:.1}" y="{:.1}">no data</text>"#,
+        MARGIN + bar + 36.0,
+        top + 10.0
+    )?;
+    writeln!(w, "</g>")?;
+    writeln!(w, "</svg>")?;
+    Ok(())
+}
@@ -0,0 +1,183 @@
+//! Conversion of the output to TopoJSON, where the boundary between two neighbourhoods is stored
+//! once as a shared arc rather than once per polygon. Coordinates are left unquantized.
+
+use geojson::FeatureCollection;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// A position by the bits of its coordinates, so it can be hashed and compared exactly.
+type Point = (u64, u64);
+
+fn point(position: &[f64]) -> Point {
+    (position[0].to_bits(), position[1].to_bits())
+}
+
+/// A ring's positions without the closing repeat of the first.
+fn open_ring(ring: &[Vec<f64>]) -> Vec<Point> {
+    let mut points = ring
+        .iter()
+        .filter(|p| p.len() >= 2)
+        .map(|p| point(p))
+        .collect::<Vec<_>>();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points
+}
+
+fn rings(value: &geojson::Value) -> Vec<&Vec<Vec<f64>>> {
+    match value {
+        geojson::Value::Polygon(rings) => rings.iter().collect(),
+        geojson::Value::MultiPolygon(polygons) => polygons.iter().flatten().collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Default)]
+struct Topology {
+    /// Points shared by more than two ring segments, where arcs have to be cut.
+    junctions: HashSet<Point>,
+    arcs: Vec<Vec<Point>>,
+    index: HashMap<Vec<Point>, usize>,
+}
+
+impl Topology {
+    fn new(collection: &FeatureCollection) -> Self {
+        let mut neighbours = HashMap::<Point, HashSet<Point>>::new();
+        let geometries = collection
+            .features
+            .iter()
+            .filter_map(|f| f.geometry.as_ref());
+        for geometry in geometries {
+            for ring in rings(&geometry.value) {
+                let ring = open_ring(ring);
+                let n = ring.len();
+                for (i, &p) in ring.iter().enumerate() {
+                    let adjacent = neighbours.entry(p).or_default();
+                    adjacent.insert(ring[(i + n - 1) % n]);
+                    adjacent.insert(ring[(i + 1) % n]);
+                }
+            }
+        }
+        Topology {
+            junctions: neighbours
+                .into_iter()
+                .filter(|(_, adjacent)| adjacent.len() > 2)
+                .map(|(p, _)| p)
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// The index of an arc, reusing an identical or reversed one if it was seen before. Reversed
+    /// arcs are referred to by their ones' complement, as TopoJSON specifies.
+    fn arc(&mut self, arc: Vec<Point>) -> i64 {
+        if let Some(&i) = self.index.get(&arc) {
+            return i as i64;
+        }
+        let reversed = arc.iter().rev().copied().collect::<Vec<_>>();
+        if let Some(&i) = self.index.get(&reversed) {
+            return !(i as i64);
+        }
+        let i = self.arcs.len();
+        self.index.insert(arc.clone(), i);
+        self.arcs.push(arc);
+        i as i64
+    }
+
+    /// Cuts a ring into arcs at its junctions.
+    fn ring(&mut self, ring: &[Vec<f64>]) -> Vec<i64> {
+        let ring = open_ring(ring);
+        let n = ring.len();
+        let cuts = (0..n)
+            .filter(|&i| self.junctions.contains(&ring[i]))
+            .collect::<Vec<_>>();
+        if cuts.is_empty() {
+            // a ring sharing no boundary segments starts at its smallest point, so a duplicate of
+            // it elsewhere starts in the same place
+            let start = (0..n).min_by_key(|&i| ring[i]).unwrap_or(0);
+            let arc = (0..=n).map(|i| ring[(start + i) % n]).collect();
+            return vec![self.arc(arc)];
+        }
+        let mut arcs = Vec::with_capacity(cuts.len());
+        for (k, &from) in cuts.iter().enumerate() {
+            let to = cuts[(k + 1) % cuts.len()];
+            let len = (to + n - from) % n;
+            let len = if len == 0 { n } else { len };
+            let arc = (0..=len).map(|i| ring[(from + i) % n]).collect();
+            arcs.push(self.arc(arc));
+        }
+        arcs
+    }
+
+    fn geometry(&mut self, value: Option<&geojson::Value>) -> Map<String, Value> {
+        let mut geometry = Map::new();
+        match value {
+            Some(geojson::Value::Polygon(rings)) => {
+                let arcs = rings.iter().map(|r| self.ring(r)).collect::<Vec<_>>();
+                geometry.insert("type".to_owned(), "Polygon".into());
+                geometry.insert("arcs".to_owned(), json!(arcs));
+            }
+            Some(geojson::Value::MultiPolygon(polygons)) => {
+                let arcs = polygons
+                    .iter()
+                    .map(|rings| rings.iter().map(|r| self.ring(r)).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                geometry.insert("type".to_owned(), "MultiPolygon".into());
+                geometry.insert("arcs".to_owned(), json!(arcs));
+            }
+            Some(geojson::Value::Point(position)) => {
+                geometry.insert("type".to_owned(), "Point".into());
+                geometry.insert("coordinates".to_owned(), json!(position));
+            }
+            _ => {
+                geometry.insert("type".to_owned(), Value::Null);
+            }
+        }
+        geometry
+    }
+}
+
+/// Converts a collection to a TopoJSON topology with a single `neighbourhoods` object. Every
+/// feature keeps its properties, and any foreign members of the collection are kept on the
+/// topology. Geometries other than polygons and points are written as null geometries.
+pub fn to_topology(collection: &FeatureCollection) -> Value {
+    let mut topology = Topology::new(collection);
+    let geometries = collection
+        .features
+        .iter()
+        .map(|feature| {
+            let mut geometry = topology.geometry(feature.geometry.as_ref().map(|g| &g.value));
+            if let Some(id) = &feature.id {
+                geometry.insert("id".to_owned(), json!(id));
+            }
+            if let Some(properties) = &feature.properties {
+                geometry.insert("properties".to_owned(), properties.clone().into());
+            }
+            Value::Object(geometry)
+        })
+        .collect::<Vec<_>>();
+    let arcs = topology
+        .arcs
+        .iter()
+        .map(|arc| {
+            arc.iter()
+                .map(|&(x, y)| json!([f64::from_bits(x), f64::from_bits(y)]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = collection.foreign_members.clone().unwrap_or_default();
+    output.insert("type".to_owned(), "Topology".into());
+    output.insert(
+        "objects".to_owned(),
+        json!({
+            "neighbourhoods": {
+                "type": "GeometryCollection",
+                "geometries": geometries,
+            }
+        }),
+    );
+    output.insert("arcs".to_owned(), arcs.into());
+    Value::Object(output)
+}
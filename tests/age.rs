@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use toronto_covid::age::{mean_age, standardized_rate, unknown_age_count, AgeBands};
+use toronto_covid::AgeGroup;
+
+const GROUPS: [AgeGroup; 9] = [
+    AgeGroup::LtEq19,
+    AgeGroup::R20to29,
+    AgeGroup::R30to39,
+    AgeGroup::R40to49,
+    AgeGroup::R50to59,
+    AgeGroup::R60to69,
+    AgeGroup::R70to79,
+    AgeGroup::R80to89,
+    AgeGroup::R90Plus,
+];
+
+/// `young` in the 19 and under group and `rest` in every other.
+fn bands(young: u32, rest: u32) -> AgeBands {
+    GROUPS
+        .iter()
+        .map(|&g| (g, if g == AgeGroup::LtEq19 { young } else { rest }))
+        .collect()
+}
+
+#[test]
+fn standardizes_against_the_reference_population() {
+    // weights of 6,000 / 10,000 for 19 and under and 500 / 10,000 for every other group
+    let standard = bands(6_000, 500);
+    let population = bands(2_000, 1_000);
+    let cases = BTreeMap::from([
+        (AgeGroup::LtEq19, 20),
+        (AgeGroup::R90Plus, 50),
+        (AgeGroup::Unknown, 7),
+    ]);
+    // 20 / 2,000 * 0.6 + 50 / 1,000 * 0.05 = 0.0085, where the crude rate is 70 / 10,000 = 0.007
+    let rate = standardized_rate(&cases, &population, &standard).unwrap();
+    assert!((rate - 850.0).abs() < 1e-9, "{}", rate);
+}
+
+#[test]
+fn needs_a_population_for_every_group_with_cases() {
+    let cases = BTreeMap::from([(AgeGroup::R90Plus, 1)]);
+    let mut population = bands(2_000, 1_000);
+    population.remove(&AgeGroup::R90Plus);
+    assert_eq!(
+        standardized_rate(&cases, &population, &bands(6_000, 500)),
+        None
+    );
+    assert_eq!(
+        standardized_rate(&cases, &bands(2_000, 1_000), &AgeBands::new()),
+        None
+    );
+}
+
+#[test]
+fn mean_age_weights_each_group_by_its_cases() {
+    let cases = BTreeMap::from([
+        (AgeGroup::LtEq19, 2),
+        (AgeGroup::R50to59, 1),
+        (AgeGroup::R90Plus, 1),
+        (AgeGroup::Unknown, 3),
+    ]);
+    // (2 * 10 + 55 + 95) / 4
+    assert_eq!(mean_age(&cases), Some(42.5));
+    // the three of unknown age and one with no age group at all
+    assert_eq!(unknown_age_count(8, &cases), 4);
+}
+
+#[test]
+fn mean_age_of_only_unknown_ages_is_none() {
+    let cases = BTreeMap::from([(AgeGroup::Unknown, 3)]);
+    assert_eq!(mean_age(&cases), None);
+    assert_eq!(unknown_age_count(3, &cases), 3);
+}
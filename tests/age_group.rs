@@ -0,0 +1,18 @@
+use toronto_covid::AgeGroup;
+
+#[test]
+fn unicode_youngest_bucket_round_trips() {
+    let group: AgeGroup = serde_json::from_str("\"\u{2264}19\"").unwrap();
+    assert_eq!(group, AgeGroup::LtEq19);
+    assert_eq!(serde_json::to_string(&group).unwrap(), "\"≤19\"");
+    assert_eq!(group.midpoint(), Some(10.0));
+}
+
+#[test]
+fn unrecognized_label_is_unknown() {
+    for label in ["\"<19\"", "\"100+\"", "\"\""] {
+        let group: AgeGroup = serde_json::from_str(label).unwrap();
+        assert_eq!(group, AgeGroup::Unknown);
+        assert_eq!(group.midpoint(), None);
+    }
+}
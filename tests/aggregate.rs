@@ -0,0 +1,111 @@
+use serde_json::json;
+use toronto_covid::{aggregate, is_valid_fsa, CovidEntry, Names};
+
+fn case(id: u32, neighbourhood: Option<&str>, fsa: Option<&str>) -> CovidEntry {
+    serde_json::from_value(json!({
+        "_id": id,
+        "Outbreak Associated": "Sporadic",
+        "Age Group": "50-59",
+        "Neighbourhood Name": neighbourhood,
+        "FSA": fsa,
+        "Client Gender": "FEMALE",
+        "Outcome": "RESOLVED",
+        "Episode Date": "2020-04-01",
+        "Reported Date": "2020-04-03",
+        "Classification": "CONFIRMED",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn counts_cases_per_fsa() {
+    let aggregates = aggregate(
+        vec![
+            case(1, Some("Wychwood"), Some("M5V")),
+            case(2, Some("Annex"), Some("M5V")),
+            case(3, Some("Annex"), None),
+        ],
+        &Names::default(),
+    );
+    assert_eq!(aggregates.per_fsa.len(), 1);
+    assert_eq!(aggregates.per_fsa["M5V"], 2);
+}
+
+#[test]
+fn crosswalk_counts_neighbourhoods_within_an_fsa() {
+    let aggregates = aggregate(
+        vec![
+            case(1, Some("Wychwood"), Some("M6C")),
+            case(2, Some("Casa Loma"), Some("m6c")),
+            case(3, Some("Wychwood"), Some("M6C")),
+            case(4, None, Some("M6C")),
+            case(5, Some("Annex"), None),
+        ],
+        &Names::default(),
+    );
+    assert_eq!(aggregates.per_fsa_neighbourhood.len(), 1);
+    let m6c = &aggregates.per_fsa_neighbourhood["M6C"];
+    assert_eq!(m6c.len(), 2);
+    assert_eq!(m6c["Wychwood"], 2);
+    assert_eq!(m6c["Casa Loma"], 1);
+    assert_eq!(aggregates.per_fsa["M6C"], 4);
+}
+
+#[test]
+fn fsa_validation() {
+    assert!(is_valid_fsa("M5V"));
+    assert!(is_valid_fsa("m6c"));
+    assert!(!is_valid_fsa("12A"));
+    assert!(!is_valid_fsa("M5"));
+    assert!(!is_valid_fsa("M5V 3L9"));
+}
+
+#[test]
+fn invalid_fsas_can_be_excluded() {
+    let mut aggregates = aggregate(
+        vec![
+            case(1, Some("Wychwood"), Some("M6C")),
+            case(2, Some("Wychwood"), Some("12A")),
+            case(3, Some("Annex"), Some("12A")),
+        ],
+        &Names::default(),
+    );
+    assert_eq!(aggregates.invalid_fsas(), [("12A", 2)]);
+    aggregates.exclude_invalid_fsas();
+    assert!(aggregates.invalid_fsas().is_empty());
+    assert_eq!(aggregates.per_fsa.len(), 1);
+    assert!(!aggregates.per_fsa_neighbourhood.contains_key("12A"));
+    assert_eq!(aggregates.per_neighbourhood["Wychwood"].total, 2);
+}
+
+#[test]
+fn no_information_is_not_a_neighbourhood() {
+    assert_eq!(
+        case(1, Some("Wychwood"), None).named_neighbourhood(),
+        Some("Wychwood")
+    );
+    assert_eq!(
+        case(2, Some(" no  INFORMATION"), None).named_neighbourhood(),
+        None
+    );
+    assert_eq!(case(3, None, None).named_neighbourhood(), None);
+}
+
+#[test]
+fn confirmed_only_leaves_out_probable_cases() {
+    let classified = |id, classification: Option<&str>| {
+        let mut entry = serde_json::to_value(case(id, Some("Wychwood"), None)).unwrap();
+        entry["Classification"] = json!(classification);
+        serde_json::from_value::<CovidEntry>(entry).unwrap()
+    };
+    let covid = [
+        classified(1, Some("CONFIRMED")),
+        classified(2, Some("PROBABLE")),
+        classified(3, Some("CONFIRMED")),
+        classified(4, None),
+    ];
+    let confirmed = covid.iter().filter(|e| e.is_confirmed()).cloned();
+    let aggregates = aggregate(confirmed, &Names::default());
+    assert_eq!(aggregates.total, 2);
+    assert_eq!(aggregates.per_neighbourhood["Wychwood"].ids, [1, 3]);
+}
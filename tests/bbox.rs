@@ -0,0 +1,62 @@
+use serde_json::json;
+use toronto_covid::geometry::bbox;
+use toronto_covid::{build_output, CensusEntryCategory, Names, DEFAULT_POPULATION_FIELD};
+
+#[test]
+fn multipolygon_bbox_covers_every_part() {
+    let value = serde_json::from_value(json!({
+        "type": "MultiPolygon",
+        "coordinates": [
+            [[[-79.42, 43.60], [-79.41, 43.60], [-79.41, 43.61], [-79.42, 43.60]]],
+            [[[-79.40, 43.65], [-79.38, 43.65], [-79.38, 43.67], [-79.40, 43.65]]],
+        ],
+    }))
+    .unwrap();
+    let geometry: geojson::Geometry = value;
+    assert_eq!(bbox(&geometry.value), Some([-79.42, 43.60, -79.38, 43.67]));
+}
+
+#[test]
+fn features_carry_their_bbox() {
+    let geojson = serde_json::from_value(json!({
+        "type": "FeatureCollection",
+        "features": [{
+            "type": "Feature",
+            "properties": { "AREA_NAME": "Wychwood (94)" },
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [
+                    [[-79.43, 43.67], [-79.41, 43.67], [-79.41, 43.69], [-79.43, 43.69], [-79.43, 43.67]],
+                    // a hole, inside the outer ring, so it doesn't change the extent
+                    [[-79.425, 43.675], [-79.42, 43.675], [-79.42, 43.68], [-79.425, 43.675]],
+                ],
+            },
+        }],
+    }))
+    .unwrap();
+    let census: CensusEntryCategory = serde_json::from_value(json!({
+        "_id": 3,
+        "Characteristic": "Population, 2016",
+        "Category": "Population",
+        "Topic": "Population and dwellings",
+        "Data Source": "Census Profile 98-316-X2016001",
+        "Wychwood": "14,349",
+    }))
+    .unwrap();
+    let output = build_output(
+        &[],
+        &[census],
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let feature = &output.features[0];
+    let expected = vec![-79.43, 43.67, -79.41, 43.69];
+    assert_eq!(feature.bbox.as_ref(), Some(&expected));
+    assert_eq!(
+        feature.properties.as_ref().unwrap()["bbox"],
+        json!(expected)
+    );
+}
@@ -0,0 +1,1044 @@
+use geojson::{FeatureCollection, GeoJson};
+use serde_json::{json, Map, Value};
+use toronto_covid::geometry;
+use toronto_covid::{
+    aggregate, attach_case_ids, attach_deltas, attach_rates, build_output, census_values,
+    city_feature, dedup_features, into_feature_collection, merge_existing, populations,
+    prefix_properties, retain_neighbourhoods, suppress_small_counts, validate_names, CensusData,
+    CensusEntryCategory, CovidEntry, Dataset, GeoJsonError, Metrics, Names, NeighbourhoodSummary,
+    UnknownPolicy, CITY_OF_TORONTO, DEFAULT_POPULATION_FIELD, DEFAULT_RATE_PER,
+    NEIGHBOURHOOD_NAMES,
+};
+
+fn case(id: u32, neighbourhood: Option<&str>, outbreak: bool) -> CovidEntry {
+    serde_json::from_value(json!({
+        "_id": id,
+        "Outbreak Associated": if outbreak { "Outbreak Associated" } else { "Sporadic" },
+        "Age Group": "50-59",
+        "Neighbourhood Name": neighbourhood,
+        "FSA": "M6C",
+        "Client Gender": "FEMALE",
+        "Outcome": "RESOLVED",
+        "Episode Date": "2020-04-01",
+        "Reported Date": "2020-04-03",
+        "Classification": "CONFIRMED",
+    }))
+    .unwrap()
+}
+
+fn census() -> Vec<CensusEntryCategory> {
+    let row = |id: u32, characteristic: &str, values: Value| {
+        let mut row = json!({
+            "_id": id,
+            "Characteristic": characteristic,
+            "Category": "Population",
+            "Topic": "Population and dwellings",
+            "Data Source": "Census Profile 98-316-X2016001",
+        });
+        row.as_object_mut()
+            .unwrap()
+            .extend(values.as_object().unwrap().clone());
+        serde_json::from_value(row).unwrap()
+    };
+    vec![
+        row(
+            1,
+            "Neighbourhood Number",
+            json!({ "Wychwood": "94", "Annex": "95", "Casa Loma": "96" }),
+        ),
+        row(
+            3,
+            "Population, 2016",
+            json!({
+                "City of Toronto": "2,731,571",
+                "Wychwood": "14,349",
+                "Annex": "30,526",
+                "Casa Loma": "10,968",
+            }),
+        ),
+    ]
+}
+
+/// A small square feature with the given `AREA_NAME`.
+fn feature(area_name: &str, x: f64) -> Value {
+    json!({
+        "type": "Feature",
+        "properties": { "AREA_NAME": area_name },
+        "geometry": {
+            "type": "Polygon",
+            "coordinates": [[[x, 43.6], [x + 0.01, 43.6], [x + 0.01, 43.61], [x, 43.61], [x, 43.6]]],
+        },
+    })
+}
+
+fn neighbourhoods(features: Vec<Value>) -> GeoJson {
+    serde_json::from_value(json!({ "type": "FeatureCollection", "features": features })).unwrap()
+}
+
+fn properties<'a>(output: &'a FeatureCollection, area_name: &str) -> &'a Map<String, Value> {
+    output
+        .features
+        .iter()
+        .filter_map(|f| f.properties.as_ref())
+        .find(|p| p["AREA_NAME"] == area_name)
+        .unwrap()
+}
+
+fn covid() -> Vec<CovidEntry> {
+    vec![
+        case(1, Some("Wychwood"), true),
+        case(2, Some("Wychwood"), true),
+        case(3, Some("Wychwood"), false),
+        case(4, Some("Annex"), false),
+        case(5, Some("Mimico (includes Humber Bay Shores)"), false),
+        case(6, None, false),
+    ]
+}
+
+#[test]
+fn attaches_counts_and_rates() {
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["name"], "Wychwood");
+    assert_eq!(wychwood["neighbourhood_number"], 94);
+    assert_eq!(wychwood["population"], 14349);
+    assert_eq!(wychwood["covid_case_count"], 3);
+    assert_eq!(wychwood["covid_outbreak_count"], 2);
+    let rate = wychwood["cases_per_100k"].as_f64().unwrap();
+    assert!((rate - 3.0 / 14349.0 * 100_000.0).abs() < 1e-9);
+    let share = wychwood["covid_outbreak_share"].as_f64().unwrap();
+    assert!((share - 2.0 / 3.0).abs() < 1e-12);
+    assert_eq!(wychwood["mean_reporting_lag_days"], 2.0);
+
+    let annex = properties(&output, "Annex (95)");
+    assert_eq!(annex["covid_case_count"], 1);
+    assert_eq!(annex["covid_outbreak_share"], 0.0);
+
+    // every case counts towards the city, placed or not
+    let city_total = &output.foreign_members.as_ref().unwrap()["city_total"];
+    assert_eq!(city_total["covid_case_count"], 6);
+    assert_eq!(city_total["population"], 2731571);
+}
+
+#[test]
+fn neighbourhood_without_cases_has_zero_count() {
+    let geojson = neighbourhoods(vec![feature("Casa Loma (96)", -79.41)]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let casa_loma = properties(&output, "Casa Loma (96)");
+    assert_eq!(casa_loma["covid_case_count"], 0);
+    assert_eq!(casa_loma["cases_per_100k"], 0.0);
+    assert_eq!(casa_loma["covid_outbreak_share"], Value::Null);
+}
+
+#[test]
+fn neighbourhood_absent_from_the_covid_fixture_has_zero_count() {
+    let covid: Vec<CovidEntry> =
+        serde_json::from_str(&std::fs::read_to_string("tests/fixtures/covid.json").unwrap())
+            .unwrap();
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let output = build_output(
+        &covid,
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    assert_eq!(output.features.len(), 2);
+    assert_eq!(properties(&output, "Wychwood (94)")["covid_case_count"], 1);
+    let casa_loma = properties(&output, "Casa Loma (96)");
+    assert_eq!(casa_loma["name"], "Casa Loma");
+    assert_eq!(casa_loma["covid_case_count"], 0);
+}
+
+#[test]
+fn unmatched_neighbourhood_gets_no_data() {
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Nowhere In Particular (999)", -79.30),
+    ]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let nowhere = properties(&output, "Nowhere In Particular (999)");
+    assert_eq!(nowhere["covid_case_count"], 0);
+    assert_eq!(nowhere["population"], Value::Null);
+    assert_eq!(nowhere["cases_per_100k"], Value::Null);
+    // none of the cases from neighbourhoods without a feature end up somewhere else
+    let placed: u64 = output
+        .features
+        .iter()
+        .map(|f| {
+            f.properties.as_ref().unwrap()["covid_case_count"]
+                .as_u64()
+                .unwrap()
+        })
+        .sum();
+    assert_eq!(placed, 3);
+}
+
+#[test]
+fn misspelt_feature_name_is_matched() {
+    let geojson = neighbourhoods(vec![feature("Wychwodd (94)", -79.42)]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let wychwood = properties(&output, "Wychwodd (94)");
+    assert_eq!(wychwood["name"], "Wychwood");
+    assert_eq!(wychwood["covid_case_count"], 3);
+}
+
+#[test]
+fn misspelt_feature_name_is_unmatched_without_fuzzy() {
+    let geojson = neighbourhoods(vec![feature("Wychwodd (94)", -79.42)]);
+    let names = Names::default().with_fuzzy_threshold(None);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &names,
+    )
+    .unwrap();
+    assert_eq!(properties(&output, "Wychwodd (94)")["covid_case_count"], 0);
+
+    let geojson = neighbourhoods(vec![feature("Wychwodd (94)", -79.42)]);
+    let names = Names::default().with_fuzzy_threshold(Some(0));
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &names,
+    )
+    .unwrap();
+    assert_eq!(properties(&output, "Wychwodd (94)")["covid_case_count"], 0);
+}
+
+#[test]
+fn fuzzy_matches_are_remembered_only_for_their_threshold() {
+    let names = Names::default();
+    let run = |names: &Names| {
+        let geojson = neighbourhoods(vec![
+            feature("Wychwodd (94)", -79.42),
+            feature("Wychwodd (94)", -79.30),
+        ]);
+        let output = build_output(
+            &covid(),
+            &census(),
+            geojson,
+            DEFAULT_POPULATION_FIELD,
+            names,
+        )
+        .unwrap();
+        properties(&output, "Wychwodd (94)")["covid_case_count"].clone()
+    };
+    assert_eq!(run(&names), 3);
+    assert_eq!(run(&names), 3);
+    assert_eq!(run(&names.clone()), 3);
+    assert_eq!(run(&names.with_fuzzy_threshold(Some(0))), 0);
+}
+
+#[test]
+fn output_does_not_depend_on_feature_order() {
+    let features = vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ];
+    let mut reversed = features.clone();
+    reversed.reverse();
+    let a = build_output(
+        &covid(),
+        &census(),
+        neighbourhoods(features),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let b = build_output(
+        &covid(),
+        &census(),
+        neighbourhoods(reversed),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_string(&a).unwrap(),
+        serde_json::to_string(&b).unwrap()
+    );
+}
+
+#[test]
+fn focus_keeps_only_named_neighbourhoods() {
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let mut output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let names = vec!["wychwood".to_owned(), "  CASA LOMA ".to_owned()];
+    let missing = retain_neighbourhoods(&mut output, &names, &Names::default());
+    assert!(missing.is_empty());
+    let kept = output
+        .features
+        .iter()
+        .map(|f| f.properties.as_ref().unwrap()["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(kept, ["Casa Loma", "Wychwood"]);
+    // the citywide figures still count the neighbourhoods left out
+    let city_total = &output.foreign_members.as_ref().unwrap()["city_total"];
+    assert_eq!(city_total["covid_case_count"], 6);
+
+    let missing = retain_neighbourhoods(&mut output, &["Atlantis".to_owned()], &Names::default());
+    assert_eq!(missing, ["Atlantis"]);
+}
+
+#[test]
+fn no_information_cases_are_unassigned() {
+    let mut covid = covid();
+    covid.push(case(7, Some("No Information"), false));
+    covid.push(case(8, Some("NO INFORMATION"), false));
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+    ]);
+    let output = build_output(
+        &covid,
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let members = output.foreign_members.as_ref().unwrap();
+    // the two sentinel rows and the one without a neighbourhood
+    assert_eq!(members["unassigned_cases"], 3);
+    assert_eq!(members["city_total"]["covid_case_count"], 8);
+}
+
+#[test]
+fn prefix_applies_to_added_properties() {
+    let geojson = neighbourhoods(vec![feature("Wychwood (94)", -79.42)]);
+    let mut output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let original = std::iter::once("AREA_NAME".to_owned()).collect();
+    prefix_properties(&mut output, "covid_", &original);
+
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["covid_population"], 14349);
+    assert_eq!(wychwood["covid_covid_case_count"], 3);
+    assert_eq!(wychwood["covid_name"], "Wychwood");
+    assert!(!wychwood.contains_key("population"));
+    assert!(!wychwood.contains_key("covid_AREA_NAME"));
+}
+
+#[test]
+fn merge_keeps_hand_added_properties() {
+    let features = || {
+        neighbourhoods(vec![
+            feature("Wychwood (94)", -79.42),
+            feature("Annex (95)", -79.40),
+        ])
+    };
+    let mut existing = build_output(
+        &covid(),
+        &census(),
+        features(),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    for feature in existing.features.iter_mut() {
+        let properties = feature.properties.as_mut().unwrap();
+        if properties["AREA_NAME"] == "Wychwood (94)" {
+            properties.insert("notes".to_owned(), "new clinic opened in May".into());
+            properties.insert("covid_case_count".to_owned(), 999.into());
+        }
+    }
+
+    let mut output = build_output(
+        &covid(),
+        &census(),
+        features(),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let known = output
+        .features
+        .iter()
+        .flat_map(|f| f.properties.as_ref().unwrap().keys().cloned())
+        .collect();
+    assert_eq!(
+        merge_existing(&mut output, existing, &known, &Names::default()),
+        1
+    );
+
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["notes"], "new clinic opened in May");
+    assert_eq!(wychwood["covid_case_count"], 3);
+    assert!(!properties(&output, "Annex (95)").contains_key("notes"));
+}
+
+#[test]
+fn duplicate_features_are_merged_or_dropped() {
+    let features = || {
+        let geojson = neighbourhoods(vec![
+            feature("Wychwood (94)", -79.42),
+            feature("Annex (95)", -79.40),
+            feature("WYCHWOOD (94)", -79.43),
+        ]);
+        into_feature_collection(geojson).unwrap()
+    };
+
+    let mut kept_first = features();
+    assert_eq!(
+        dedup_features(&mut kept_first, false, &Names::default()),
+        ["Wychwood"]
+    );
+    assert_eq!(kept_first.features.len(), 2);
+    let wychwood = kept_first.features[0].geometry.as_ref().unwrap();
+    assert!(matches!(wychwood.value, geojson::Value::Polygon(_)));
+
+    let mut merged = features();
+    assert_eq!(
+        dedup_features(&mut merged, true, &Names::default()),
+        ["Wychwood"]
+    );
+    assert_eq!(merged.features.len(), 2);
+    let wychwood = &merged.features[0];
+    assert_eq!(
+        wychwood.properties.as_ref().unwrap()["AREA_NAME"],
+        "Wychwood (94)"
+    );
+    match &wychwood.geometry.as_ref().unwrap().value {
+        geojson::Value::MultiPolygon(parts) => assert_eq!(parts.len(), 2),
+        other => panic!("expected a multipolygon, got {:?}", other),
+    }
+
+    // the merged feature is joined once, with the neighbourhood's cases
+    let output = build_output(
+        &covid(),
+        &census(),
+        merged.into(),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    assert_eq!(output.features.len(), 2);
+    assert_eq!(properties(&output, "Wychwood (94)")["covid_case_count"], 3);
+}
+
+#[test]
+fn metrics_keep_only_the_listed_properties() {
+    let geojson = neighbourhoods(vec![feature("Wychwood (94)", -79.42)]);
+    let mut output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let metrics = "rate, cfr".parse::<Metrics>().unwrap();
+    metrics.retain(&mut output);
+
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert!(wychwood.contains_key("cases_per_100k"));
+    assert!(wychwood.contains_key("covid_rate"));
+    assert!(wychwood.contains_key("covid_cfr"));
+    for key in [
+        "covid_case_count",
+        "covid_outbreak_share",
+        "population_density",
+        "rate_rank",
+        "weekly_growth_pct",
+    ]
+    .iter()
+    {
+        assert!(!wychwood.contains_key(*key), "{} wasn't asked for", key);
+    }
+    assert_eq!(wychwood["name"], "Wychwood");
+    assert_eq!(wychwood["population"], 14349);
+
+    let e = "rate,density,sparkle".parse::<Metrics>().unwrap_err();
+    assert!(e.contains("`sparkle`"));
+    assert!(e.contains("outbreak_share"));
+}
+
+#[test]
+fn dataset_looks_up_neighbourhoods_by_name() {
+    let covid = vec![
+        case(1, Some("Wychwood"), true),
+        case(2, Some("Wychwood"), false),
+        case(3, Some("Annex"), false),
+    ];
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+    ]);
+    let dataset = Dataset::new(
+        &covid,
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        Names::default(),
+    )
+    .unwrap();
+
+    let wychwood = dataset.neighbourhood_stats("  wychwood ").unwrap();
+    assert_eq!(wychwood.name, "Wychwood");
+    assert_eq!(wychwood.covid_case_count, 2);
+    assert_eq!(wychwood.population, Some(14349));
+    // within the fuzzy threshold
+    let annex = dataset.neighbourhood_stats("Anex").unwrap();
+    assert_eq!(annex.covid_case_count, 1);
+
+    assert_eq!(dataset.neighbourhood_stats("Casa Loma"), None);
+    assert_eq!(dataset.neighbourhood_stats("Springfield"), None);
+    assert_eq!(dataset.neighbourhood_stats(""), None);
+}
+
+#[test]
+fn case_ids_are_listed_unless_suppressed() {
+    let covid = vec![
+        case(7, Some("Wychwood"), true),
+        case(3, Some("Wychwood"), false),
+        case(5, Some("Annex"), false),
+        case(9, None, false),
+    ];
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let aggregates = aggregate(covid.iter().cloned(), &Names::default());
+    let mut output = build_output(
+        &covid,
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    attach_case_ids(
+        &mut output,
+        &aggregates.per_neighbourhood,
+        10,
+        &Names::default(),
+    );
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["case_ids"], json!([3, 7]));
+    assert_eq!(wychwood["case_ids_truncated"], false);
+    assert_eq!(properties(&output, "Casa Loma (96)")["case_ids"], json!([]));
+
+    attach_case_ids(
+        &mut output,
+        &aggregates.per_neighbourhood,
+        1,
+        &Names::default(),
+    );
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["case_ids"], json!([3]));
+    assert_eq!(wychwood["case_ids_truncated"], true);
+
+    suppress_small_counts(&mut output, 2, &[]);
+    assert_eq!(properties(&output, "Annex (95)")["case_ids"], Value::Null);
+    assert_eq!(properties(&output, "Wychwood (94)")["case_ids"], json!([3]));
+}
+
+#[test]
+fn rates_against_another_census_characteristic() {
+    let mut census = census();
+    census.push(
+        serde_json::from_value(json!({
+            "_id": 60,
+            "Characteristic": "  Private households",
+            "Category": "Families, households and marital status",
+            "Topic": "Household and dwelling characteristics",
+            "Data Source": "Census Profile 98-316-X2016001",
+            "Wychwood": "5,000",
+            "Annex": "$4,000",
+            "Casa Loma": "0",
+        }))
+        .unwrap(),
+    );
+    let covid = vec![
+        case(1, Some("Wychwood"), true),
+        case(2, Some("Wychwood"), false),
+        case(3, Some("Annex"), false),
+    ];
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let aggregates = aggregate(covid.iter().cloned(), &Names::default());
+    let mut output = build_output(
+        &covid,
+        &census,
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let households = census_values(&census, "Private households", &Names::default()).unwrap();
+    attach_rates(
+        &mut output,
+        &aggregates.per_neighbourhood,
+        &households,
+        &Names::default(),
+    );
+    assert_eq!(properties(&output, "Wychwood (94)")["rate_per_100k"], 40.0);
+    assert_eq!(properties(&output, "Annex (95)")["rate_per_100k"], 25.0);
+    assert_eq!(
+        properties(&output, "Casa Loma (96)")["rate_per_100k"],
+        Value::Null
+    );
+    // the population-based rate is left alone
+    let wychwood = properties(&output, "Wychwood (94)")["cases_per_100k"]
+        .as_f64()
+        .unwrap();
+    assert!((wychwood - 2.0 * 100_000.0 / 14349.0).abs() < 1e-9);
+}
+
+#[test]
+fn original_properties_keep_their_order() {
+    let mut feature = feature("Wychwood (94)", -79.42);
+    feature["properties"] = json!({
+        "_id": 11,
+        "AREA_SHORT_CODE": "094",
+        "AREA_NAME": "Wychwood (94)",
+        "Shape__Area": 1.5,
+        "AREA_ID": 2,
+    });
+    let covid = vec![case(1, Some("Wychwood"), false)];
+    let output = build_output(
+        &covid,
+        &census(),
+        neighbourhoods(vec![feature]),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let keys = properties(&output, "Wychwood (94)")
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys[..6],
+        [
+            "_id",
+            "AREA_SHORT_CODE",
+            "AREA_NAME",
+            "Shape__Area",
+            "AREA_ID",
+            "name"
+        ]
+    );
+    // the computed properties follow in the same order every run
+    let again = build_output(
+        &covid,
+        &census(),
+        neighbourhoods(vec![json!({
+            "type": "Feature",
+            "properties": properties(&output, "Wychwood (94)"),
+            "geometry": null,
+        })]),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let again = properties(&again, "Wychwood (94)")
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    assert_eq!(keys, again);
+}
+
+#[test]
+fn city_feature_sums_the_neighbourhoods() {
+    let covid = vec![
+        case(1, Some("Wychwood"), true),
+        case(2, Some("Wychwood"), false),
+        case(3, Some("Annex"), true),
+        case(4, Some("Casa Loma"), false),
+        case(5, None, false),
+    ];
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let aggregates = aggregate(covid.iter().cloned(), &Names::default());
+    let output = build_output(
+        &covid,
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let census = CensusData::new(census(), &Names::default(), DEFAULT_POPULATION_FIELD).unwrap();
+    let city = city_feature(
+        &output,
+        &aggregates.per_neighbourhood,
+        &census,
+        DEFAULT_RATE_PER,
+        UnknownPolicy::default(),
+        &Names::default(),
+    );
+
+    let properties = city.properties.as_ref().unwrap();
+    assert_eq!(properties["name"], "City of Toronto");
+    let parts = output
+        .features
+        .iter()
+        .map(|f| {
+            f.properties.as_ref().unwrap()["covid_case_count"]
+                .as_u64()
+                .unwrap()
+        })
+        .sum::<u64>();
+    assert_eq!(properties["covid_case_count"], parts);
+    assert_eq!(properties["covid_case_count"], 4);
+    assert_eq!(properties["covid_outbreak_count"], 2);
+    assert_eq!(properties["population"], 14349 + 30526 + 10968);
+    let rate = properties["cases_per_100k"].as_f64().unwrap();
+    assert!((rate - 4.0 * 100_000.0 / 55843.0).abs() < 1e-9);
+    // the three adjoining squares dissolve into one rectangle
+    match &city.geometry.as_ref().unwrap().value {
+        geojson::Value::Polygon(rings) => {
+            assert_eq!(rings.len(), 1);
+            assert_eq!(rings[0].len(), 5);
+        }
+        other => panic!("expected a polygon, got {:?}", other),
+    }
+}
+
+#[test]
+fn city_feature_counts_a_neighbourhood_with_several_features_once() {
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Wychwood (94)", -79.30),
+    ]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let aggregates = aggregate(covid(), &Names::default());
+    let census = CensusData::new(census(), &Names::default(), DEFAULT_POPULATION_FIELD).unwrap();
+    let city = city_feature(
+        &output,
+        &aggregates.per_neighbourhood,
+        &census,
+        DEFAULT_RATE_PER,
+        UnknownPolicy::default(),
+        &Names::default(),
+    );
+
+    let properties = city.properties.as_ref().unwrap();
+    assert_eq!(properties["covid_case_count"], 3 + 1);
+    assert_eq!(properties["population"], 14349 + 30526);
+    // every feature is the same size, and only two of them count towards the area
+    let area = geometry::area_km2(&output.features[0].geometry.as_ref().unwrap().value).unwrap();
+    let density = properties["population_density"].as_f64().unwrap();
+    assert!((density - (14349.0 + 30526.0) / (2.0 * area)).abs() < 1e-6);
+    // the hull spans only the first Wychwood and the Annex, not the far one at -79.30
+    let hull = geometry::bbox(&city.geometry.as_ref().unwrap().value).unwrap();
+    assert!((hull[0] - -79.42).abs() < 1e-9 && (hull[2] - -79.39).abs() < 1e-9);
+}
+
+#[test]
+fn aliases_are_used_only_where_given() {
+    let geojson = || neighbourhoods(vec![feature("Seaton Village (95)", -79.40)]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson(),
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        properties(&output, "Seaton Village (95)")["covid_case_count"],
+        0
+    );
+
+    let names = Names::with_aliases(vec![("Seaton Village".to_owned(), "Annex".to_owned())]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson(),
+        DEFAULT_POPULATION_FIELD,
+        &names,
+    )
+    .unwrap();
+    let annex = properties(&output, "Seaton Village (95)");
+    assert_eq!(annex["name"], "Annex");
+    assert_eq!(annex["covid_case_count"], 1);
+}
+
+#[test]
+fn city_of_toronto_is_not_a_feature() {
+    let names = Names::default();
+    assert!(NEIGHBOURHOOD_NAMES.contains(&CITY_OF_TORONTO));
+    assert!(!names.is_known(CITY_OF_TORONTO));
+
+    let features = names
+        .known()
+        .iter()
+        .enumerate()
+        .map(|(i, name)| feature(name, -79.6 + i as f64 * 0.01))
+        .collect();
+    let output = build_output(
+        &covid(),
+        &census(),
+        neighbourhoods(features),
+        DEFAULT_POPULATION_FIELD,
+        &names,
+    )
+    .unwrap();
+    assert_eq!(output.features.len(), names.known().len());
+    assert!(validate_names(&output.features, &names).is_empty());
+    assert!(output
+        .features
+        .iter()
+        .all(|f| f.properties.as_ref().unwrap()["name"] != CITY_OF_TORONTO));
+
+    // the census's citywide column becomes the city total instead
+    let city_total = &output.foreign_members.as_ref().unwrap()["city_total"];
+    assert_eq!(city_total["population"], 2731571);
+}
+
+#[test]
+fn accepts_a_collection_or_a_bare_feature_but_not_a_geometry() {
+    let collection = neighbourhoods(vec![feature("Wychwood (94)", -79.42)]);
+    let collection = into_feature_collection(collection).unwrap();
+    assert_eq!(collection.features.len(), 1);
+
+    let bare: GeoJson = serde_json::from_value(feature("Wychwood (94)", -79.42)).unwrap();
+    assert!(matches!(bare, GeoJson::Feature(_)));
+    let output = build_output(
+        &covid(),
+        &census(),
+        bare,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    assert_eq!(output.features.len(), 1);
+    assert_eq!(properties(&output, "Wychwood (94)")["covid_case_count"], 3);
+
+    let geometry: GeoJson =
+        serde_json::from_value(feature("Wychwood (94)", -79.42)["geometry"].clone()).unwrap();
+    assert!(matches!(
+        into_feature_collection(geometry.clone()),
+        Err(GeoJsonError::BareGeometry)
+    ));
+    assert!(build_output(
+        &covid(),
+        &census(),
+        geometry,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default()
+    )
+    .is_err());
+}
+
+#[test]
+fn feature_properties_round_trip_through_the_summary() {
+    let geojson = neighbourhoods(vec![feature("Wychwood (94)", -79.42)]);
+    let output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let properties = properties(&output, "Wychwood (94)");
+
+    let summary: NeighbourhoodSummary =
+        serde_json::from_value(Value::Object(properties.clone())).unwrap();
+    assert_eq!(summary.name, "Wychwood");
+    assert_eq!(summary.covid_case_count, 3);
+    assert_eq!(summary.population, Some(14349));
+    let written = serde_json::to_value(&summary).unwrap();
+    for (key, value) in written.as_object().unwrap() {
+        assert_eq!(properties.get(key), Some(value), "{}", key);
+    }
+}
+
+#[test]
+fn deltas_between_two_snapshots() {
+    let old = vec![
+        case(1, Some("Wychwood"), false),
+        case(2, Some("Annex"), false),
+        case(3, Some("Annex"), false),
+    ];
+    let new = (1..=6)
+        .map(|id| case(id, Some("Wychwood"), false))
+        .collect::<Vec<_>>();
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let mut output = build_output(
+        &new,
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+    let populations = populations(census(), &Names::default(), DEFAULT_POPULATION_FIELD).unwrap();
+
+    attach_deltas(
+        &mut output,
+        &aggregate(new, &Names::default()).per_neighbourhood,
+        &aggregate(old, &Names::default()).per_neighbourhood,
+        &populations,
+        &Names::default(),
+    );
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["covid_case_delta"], 5);
+    let rate = wychwood["covid_rate_delta"].as_f64().unwrap();
+    assert!((rate - 5.0 / 14_349.0 * 100_000.0).abs() < 1e-9);
+    assert_eq!(properties(&output, "Annex (95)")["covid_case_delta"], -2);
+    assert_eq!(properties(&output, "Casa Loma (96)")["covid_case_delta"], 0);
+    assert_eq!(
+        properties(&output, "Casa Loma (96)")["covid_rate_delta"],
+        0.0
+    );
+}
+
+#[test]
+fn output_is_byte_stable_and_sorted_by_name() {
+    let run = |features: Vec<Value>| {
+        let output = build_output(
+            &covid(),
+            &census(),
+            neighbourhoods(features),
+            DEFAULT_POPULATION_FIELD,
+            &Names::default(),
+        )
+        .unwrap();
+        GeoJson::from(output).to_string()
+    };
+    let features = vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ];
+    let first = run(features.clone());
+    assert_eq!(run(features.clone()), first);
+    assert_eq!(run(features.into_iter().rev().collect()), first);
+
+    let output: FeatureCollection = serde_json::from_str(&first).unwrap();
+    let order = output
+        .features
+        .iter()
+        .map(|f| f.properties.as_ref().unwrap()["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(order, ["Annex", "Casa Loma", "Wychwood"]);
+}
+
+#[test]
+fn suppresses_counts_below_the_threshold() {
+    let geojson = neighbourhoods(vec![
+        feature("Wychwood (94)", -79.42),
+        feature("Annex (95)", -79.40),
+        feature("Casa Loma (96)", -79.41),
+    ]);
+    let mut output = build_output(
+        &covid(),
+        &census(),
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let mut suppressed = suppress_small_counts(&mut output, 3, &[]);
+    suppressed.sort();
+    assert_eq!(suppressed, ["Annex", "Casa Loma"]);
+
+    let wychwood = properties(&output, "Wychwood (94)");
+    assert_eq!(wychwood["suppressed"], false);
+    assert_eq!(wychwood["covid_case_count"], 3);
+    let annex = properties(&output, "Annex (95)");
+    assert_eq!(annex["suppressed"], true);
+    assert_eq!(annex["covid_case_count"], Value::Null);
+    assert_eq!(annex["cases_per_100k"], Value::Null);
+    assert_eq!(annex["population"], 30526);
+    assert_eq!(
+        properties(&output, "Casa Loma (96)")["covid_case_count"],
+        Value::Null
+    );
+
+    let city_total = &output.foreign_members.as_ref().unwrap()["city_total"];
+    assert_eq!(city_total["covid_case_count"], 6);
+}
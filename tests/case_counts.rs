@@ -0,0 +1,142 @@
+use serde_json::{json, Value};
+use toronto_covid::{CaseCounts, CovidEntry, NeighbourhoodSummary, UnknownPolicy};
+
+/// A case in Wychwood with `fields` set on top of the columns every case has.
+fn case(id: u32, fields: Value) -> CovidEntry {
+    let mut entry = json!({
+        "_id": id,
+        "Outbreak Associated": "Sporadic",
+        "Age Group": "50-59",
+        "Neighbourhood Name": "Wychwood",
+    });
+    entry
+        .as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    serde_json::from_value(entry).unwrap()
+}
+
+fn counts(cases: &[CovidEntry]) -> CaseCounts {
+    let mut counts = CaseCounts::default();
+    for case in cases.iter() {
+        counts.add(case);
+    }
+    counts
+}
+
+fn summary(counts: &CaseCounts, policy: UnknownPolicy) -> NeighbourhoodSummary {
+    NeighbourhoodSummary::new(
+        "Wychwood".to_owned(),
+        counts,
+        Some(10_000),
+        None,
+        None,
+        100_000,
+        policy,
+    )
+}
+
+#[test]
+fn counts_each_gender() {
+    let counts = counts(&[
+        case(1, json!({ "Client Gender": "MALE" })),
+        case(2, json!({ "Client Gender": "FEMALE" })),
+        case(3, json!({ "Client Gender": " female " })),
+        case(4, json!({ "Client Gender": "TRANSGENDER" })),
+        case(5, json!({ "Client Gender": "UNKNOWN" })),
+        case(6, json!({ "Client Gender": "" })),
+        case(7, json!({})),
+    ]);
+    assert_eq!(counts.male, 1);
+    assert_eq!(counts.female, 2);
+    assert_eq!(counts.other_gender, 1);
+    assert_eq!(counts.unknown_gender, 3);
+
+    let summary = summary(&counts, UnknownPolicy::IncludeAsOther);
+    assert_eq!(summary.covid_cases_male, 1);
+    assert_eq!(summary.covid_cases_female, 2);
+    assert_eq!(summary.covid_cases_other, 4);
+}
+
+#[test]
+fn tallies_hospitalization_yes_answers_only() {
+    let unexpected = case(1, json!({ "Ever in ICU": "maybe", "Ever Intubated": "" }));
+    assert_eq!(unexpected.ever_icu, None);
+    assert_eq!(unexpected.ever_intubated, None);
+    assert_eq!(unexpected.ever_hospitalized, None);
+
+    let counts = counts(&[
+        case(
+            1,
+            json!({ "Ever Hospitalized": "Yes", "Ever in ICU": "Yes", "Ever Intubated": "No" }),
+        ),
+        case(
+            2,
+            json!({ "Ever Hospitalized": "yes", "Ever in ICU": "No", "Ever Intubated": "" }),
+        ),
+        case(
+            3,
+            json!({ "Ever Hospitalized": "No", "Ever in ICU": "maybe" }),
+        ),
+        case(
+            4,
+            json!({ "Ever Hospitalized": "", "Ever Intubated": null }),
+        ),
+    ]);
+    assert_eq!(counts.ever_hospitalized, 2);
+    assert_eq!(counts.ever_icu, 1);
+    assert_eq!(counts.ever_intubated, 0);
+}
+
+#[test]
+fn groups_sources_of_infection() {
+    let counts = counts(&[
+        case(1, json!({ "Source of Infection": "Community" })),
+        case(2, json!({ "Source of Infection": "Close contact" })),
+        case(3, json!({ "Source of Infection": "Close Contact" })),
+        case(
+            4,
+            json!({ "Source of Infection": "N/A - Outbreak associated" }),
+        ),
+        case(5, json!({ "Source of Infection": "Space" })),
+    ]);
+    let sources = counts.sources.into_iter().collect::<Vec<_>>();
+    assert_eq!(
+        sources,
+        [
+            ("Close Contact", 2),
+            ("Community", 1),
+            ("Other", 1),
+            ("Outbreak", 1)
+        ]
+    );
+}
+
+#[test]
+fn mean_reporting_lag_over_plausible_lags() {
+    let counts = counts(&[
+        case(
+            1,
+            json!({ "Episode Date": "2020-04-01", "Reported Date": "2020-04-03" }),
+        ),
+        case(
+            2,
+            json!({ "Episode Date": "2020-04-01", "Reported Date": "2020-04-07" }),
+        ),
+        case(
+            3,
+            json!({ "Episode Date": "2020-04-05", "Reported Date": "2020-04-01" }),
+        ),
+        case(
+            4,
+            json!({ "Episode Date": "2019-01-01", "Reported Date": "2020-04-01" }),
+        ),
+        case(5, json!({ "Episode Date": "2020-04-01" })),
+    ]);
+    assert_eq!(counts.reporting_lags, 2);
+    assert_eq!(counts.implausible_reporting_lags, 2);
+
+    let summary = summary(&counts, UnknownPolicy::Separate);
+    assert_eq!(summary.mean_reporting_lag_days, Some(4.0));
+    assert_eq!(summary.reporting_lag_excluded_count, 2);
+}
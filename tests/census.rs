@@ -0,0 +1,36 @@
+use serde_json::{Map, Value};
+use toronto_covid::CensusEntryCategory;
+
+const HEADER_COLUMNS: [&str; 5] = ["_id", "Characteristic", "Category", "Topic", "Data Source"];
+
+#[test]
+fn every_row_keeps_every_neighbourhood_value() {
+    let data = std::fs::read_to_string("neighbourhood-profiles-2016-csv.json").unwrap();
+    let census: Vec<CensusEntryCategory> = serde_json::from_str(&data).unwrap();
+    let rows: Vec<Map<String, Value>> = serde_json::from_str(&data).unwrap();
+    assert_eq!(census.len(), rows.len());
+
+    for (entry, row) in census
+        .iter()
+        .map(CensusEntryCategory::entry)
+        .zip(rows.iter())
+    {
+        assert_eq!(Value::from(entry.id), row["_id"]);
+        assert_eq!(entry.characteristic, row["Characteristic"]);
+        assert_eq!(entry.category, row["Category"]);
+        assert_eq!(entry.topic, row["Topic"]);
+        assert_eq!(entry.data_source, row["Data Source"]);
+
+        let values = row
+            .iter()
+            .filter(|(column, _)| !HEADER_COLUMNS.contains(&column.as_str()))
+            .collect::<Vec<_>>();
+        // a column for the city and for each of the 140 neighbourhoods
+        assert_eq!(values.len(), 141);
+        assert_eq!(entry.neighbourhoods.len(), values.len());
+        for (column, value) in values {
+            let expected = value.as_str().map(str::to_owned);
+            assert_eq!(entry.neighbourhoods[column], expected, "{}", column);
+        }
+    }
+}
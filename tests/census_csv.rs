@@ -0,0 +1,61 @@
+use toronto_covid::{
+    parse_census_csv, populations, CensusEntryCategory, CensusError, Names,
+    DEFAULT_POPULATION_FIELD,
+};
+
+const CENSUS: &str = "\
+_id,Category,Topic,Data Source,Characteristic,City of Toronto,Wychwood,Annex,Casa Loma
+1,Neighbourhood Information,Neighbourhood Information,City of Toronto,Neighbourhood Number,,94,95,96
+3,Population,Population and dwellings,Census Profile 98-316-X2016001,\"Population, 2016\",\"2,731,571\",\"14,349\",\"30,526\",\"10,968\"
+4,Population,Population and dwellings,Census Profile 98-316-X2016001,\"Population, 2011\",\"2,615,060\",\"14,207\",\"29,177\",\"10,444\"
+";
+
+#[test]
+fn reads_populations_from_csv() {
+    let census = parse_census_csv(CENSUS.as_bytes(), DEFAULT_POPULATION_FIELD, |_| false).unwrap();
+    assert_eq!(census.len(), 2);
+    assert!(matches!(census[1], CensusEntryCategory::Other(_)));
+    assert_eq!(census[1].entry().id, 3);
+
+    let populations = populations(census, &Names::default(), DEFAULT_POPULATION_FIELD).unwrap();
+    assert_eq!(populations.len(), 4);
+    assert_eq!(populations["City of Toronto"], 2731571);
+    assert_eq!(populations["Wychwood"], 14349);
+    assert_eq!(populations["Annex"], 30526);
+    assert_eq!(populations["Casa Loma"], 10968);
+}
+
+#[test]
+fn blank_values_are_missing() {
+    let census = parse_census_csv(CENSUS.as_bytes(), DEFAULT_POPULATION_FIELD, |_| false).unwrap();
+    let numbers = census[0].entry();
+    assert_eq!(numbers.neighbourhoods["City of Toronto"], None);
+    assert_eq!(numbers.neighbourhoods["Annex"].as_deref(), Some("95"));
+    assert!(!numbers.neighbourhoods.contains_key("Characteristic"));
+}
+
+const HEADER: &str = "_id,Category,Topic,Data Source,Characteristic,City of Toronto,Wychwood\n";
+
+#[test]
+fn population_row_is_picked_by_category_and_topic() {
+    let census = format!(
+        "{}{}{}",
+        HEADER,
+        "7,Language,Language spoken most often at home,Census,\"Population, 2016\",12,3\n",
+        "3,Population,Population and dwellings,Census,\"Population, 2016\",\"2,731,571\",\"14,349\"\n",
+    );
+    let census = parse_census_csv(census.as_bytes(), DEFAULT_POPULATION_FIELD, |_| false).unwrap();
+    let populations = populations(census, &Names::default(), DEFAULT_POPULATION_FIELD).unwrap();
+    assert_eq!(populations["Wychwood"], 14349);
+}
+
+#[test]
+fn two_matching_population_rows_are_ambiguous() {
+    let row = "3,Population,Population and dwellings,Census,\"Population, 2016\",\"2,731,571\",\"14,349\"\n";
+    let census = format!("{}{}{}", HEADER, row, row.replacen('3', "5", 1));
+    let census = parse_census_csv(census.as_bytes(), DEFAULT_POPULATION_FIELD, |_| false).unwrap();
+    assert!(matches!(
+        populations(census, &Names::default(), DEFAULT_POPULATION_FIELD),
+        Err(CensusError::AmbiguousPopulation { field, found: 2 }) if field == DEFAULT_POPULATION_FIELD
+    ));
+}
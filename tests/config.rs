@@ -0,0 +1,83 @@
+use std::ffi::OsString;
+use structopt::StructOpt;
+use toronto_covid::config::{merge, ConfigError};
+
+#[derive(StructOpt)]
+struct Args {
+    #[structopt(long = "format", default_value = "geojson")]
+    format: String,
+    #[structopt(long = "per", default_value = "100000")]
+    per: u32,
+    #[structopt(long = "covid-field", number_of_values = 1)]
+    covid_field: Vec<String>,
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+    #[structopt(long = "compact")]
+    compact: bool,
+    #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
+    verbose: u8,
+}
+
+const CONFIG: &str = r#"
+format = "csv"
+per = 1000
+covid_field = ["Currently Hospitalized", "Currently in ICU"]
+quiet = true
+compact = false
+"#;
+
+fn cli(args: &[&str]) -> Vec<OsString> {
+    std::iter::once("toronto-covid")
+        .chain(args.iter().copied())
+        .map(OsString::from)
+        .collect()
+}
+
+#[test]
+fn config_sets_defaults() {
+    let args = Args::from_iter(merge(CONFIG, cli(&[]), &[('q', "quiet")], &[]).unwrap());
+    assert_eq!(args.format, "csv");
+    assert_eq!(args.per, 1000);
+    assert_eq!(
+        args.covid_field,
+        ["Currently Hospitalized", "Currently in ICU"]
+    );
+    assert!(args.quiet);
+    assert!(!args.compact);
+}
+
+#[test]
+fn command_line_wins() {
+    let cli = cli(&["--format", "topojson", "--per=10", "-q"]);
+    let args = Args::from_iter(merge(CONFIG, cli, &[('q', "quiet")], &[]).unwrap());
+    assert_eq!(args.format, "topojson");
+    assert_eq!(args.per, 10);
+    // still taken from the file
+    assert_eq!(args.covid_field.len(), 2);
+    assert!(args.quiet);
+}
+
+#[test]
+fn config_cannot_point_elsewhere() {
+    let e = merge("config = \"other.toml\"", cli(&[]), &[], &[]).unwrap_err();
+    assert!(matches!(e, ConfigError::Nested));
+    let e = merge("[format]\nname = \"csv\"", cli(&[]), &[], &[]).unwrap_err();
+    assert!(matches!(e, ConfigError::Unsupported(_)));
+}
+
+#[test]
+fn counted_options_are_repeated() {
+    let shorts = [('q', "quiet"), ('v', "verbose")];
+    let config = |verbose: &str| format!("{}verbose = {}\n", CONFIG, verbose);
+    let args = Args::from_iter(merge(&config("2"), cli(&[]), &shorts, &["verbose"]).unwrap());
+    assert_eq!(args.verbose, 2);
+    let args = Args::from_iter(merge(&config("true"), cli(&[]), &shorts, &["verbose"]).unwrap());
+    assert_eq!(args.verbose, 1);
+    let args = Args::from_iter(merge(&config("2"), cli(&["-v"]), &shorts, &["verbose"]).unwrap());
+    assert_eq!(args.verbose, 1);
+
+    let e = merge(&config("-1"), cli(&[]), &shorts, &["verbose"]).unwrap_err();
+    assert!(matches!(e, ConfigError::Count(_)));
+    let e = merge(&config("\"loud\""), cli(&[]), &shorts, &["verbose"]).unwrap_err();
+    assert!(matches!(e, ConfigError::Count(_)));
+}
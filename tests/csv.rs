@@ -0,0 +1,45 @@
+use serde_json::json;
+use std::collections::HashMap;
+use toronto_covid::output::write_csv;
+use toronto_covid::{aggregate, CovidEntry, Names};
+
+fn case(id: u32, neighbourhood: &str) -> CovidEntry {
+    serde_json::from_value(json!({
+        "_id": id,
+        "Outbreak Associated": "Sporadic",
+        "Age Group": "50-59",
+        "Neighbourhood Name": neighbourhood,
+        "Episode Date": "2020-04-01",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn matches_golden_file() {
+    let names = Names::default();
+    let covid = vec![
+        case(1, "Wychwood"),
+        case(2, "Wychwood"),
+        case(3, "Wychwood"),
+        case(4, "Tam O'Shanter-Sullivan"),
+    ];
+    let aggregates = aggregate(covid, &names);
+    let populations = vec![
+        ("Wychwood".to_owned(), 14349),
+        ("Annex".to_owned(), 30526),
+        ("Tam O'Shanter-Sullivan".to_owned(), 27446),
+    ]
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+
+    let mut csv = Vec::new();
+    write_csv(
+        &mut csv,
+        &aggregates.per_neighbourhood,
+        &populations,
+        &names,
+    )
+    .unwrap();
+    let expected = std::fs::read_to_string("tests/fixtures/neighbourhoods.csv").unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), expected);
+}
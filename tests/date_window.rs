@@ -0,0 +1,76 @@
+use chrono::NaiveDate;
+use serde_json::json;
+use toronto_covid::output::{expand_path, Format};
+use toronto_covid::{CovidEntry, DateWindow};
+
+fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+}
+
+fn april() -> DateWindow {
+    DateWindow {
+        since: Some(date("2020-04-01")),
+        until: Some(date("2020-04-30")),
+    }
+}
+
+#[test]
+fn boundary_dates_are_included() {
+    let window = april();
+    assert!(window.contains(Some(date("2020-04-01"))));
+    assert!(window.contains(Some(date("2020-04-30"))));
+    assert!(!window.contains(Some(date("2020-03-31"))));
+    assert!(!window.contains(Some(date("2020-05-01"))));
+}
+
+#[test]
+fn undated_entries_only_pass_an_unbounded_window() {
+    let entry: CovidEntry = serde_json::from_value(json!({
+        "_id": 1,
+        "Outbreak Associated": "Sporadic",
+        "Age Group": "50-59",
+        "Episode Date": "2020-04-31",
+    }))
+    .unwrap();
+    assert_eq!(entry.episode_date, None);
+    assert!(!april().contains(entry.episode_date));
+    let since = DateWindow {
+        since: Some(date("2020-04-01")),
+        until: None,
+    };
+    assert!(!since.contains(entry.episode_date));
+    assert!(DateWindow::default().contains(entry.episode_date));
+}
+
+#[test]
+fn output_path_template_is_filled_from_the_window_and_format() {
+    let template = "docs/out-{date}.{format}";
+    assert_eq!(
+        expand_path(template, &april(), Format::GeoJson),
+        "docs/out-2020-04.geojson"
+    );
+    let window = DateWindow {
+        since: Some(date("2020-04-01")),
+        until: Some(date("2020-04-15")),
+    };
+    assert_eq!(
+        expand_path(template, &window, Format::Csv),
+        "docs/out-2020-04-01_2020-04-15.csv"
+    );
+    let window = DateWindow {
+        since: Some(date("2020-04-15")),
+        until: None,
+    };
+    assert_eq!(
+        expand_path(template, &window, Format::GeoJson),
+        "docs/out-since-2020-04-15.geojson"
+    );
+    assert_eq!(
+        expand_path(template, &DateWindow::default(), Format::GeoJson),
+        "docs/out-all.geojson"
+    );
+    assert_eq!(
+        expand_path("docs/out.geojson", &april(), Format::GeoJson),
+        "docs/out.geojson"
+    );
+}
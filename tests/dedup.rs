@@ -0,0 +1,57 @@
+use std::path::Path;
+use std::process::Command;
+
+const CASE: &str = r#"{"_id": 1, "Outbreak Associated": "Sporadic", "Age Group": "50-59", "Neighbourhood Name": "Wychwood", "FSA": "M6C", "Client Gender": "FEMALE", "Outcome": "RESOLVED", "Episode Date": "2020-04-01", "Reported Date": "2020-04-03", "Classification": "CONFIRMED"}"#;
+
+const CENSUS: &str = r#"[{"_id": 3, "Characteristic": "Population, 2016", "Category": "Population", "Topic": "Population and dwellings", "Data Source": "Census Profile 98-316-X2016001", "City of Toronto": "2,731,571", "Wychwood": "14,349"}]"#;
+
+const NEIGHBOURHOODS: &str = r#"{"type": "FeatureCollection", "features": [{"type": "Feature", "properties": {"AREA_NAME": "Wychwood (94)"}, "geometry": {"type": "Polygon", "coordinates": [[[-79.42, 43.6], [-79.41, 43.6], [-79.41, 43.61], [-79.42, 43.6]]]}}]}"#;
+
+/// Runs the binary in `dir` over the same case twice, returning the Wychwood row of its CSV output.
+fn wychwood_row(dir: &Path, extra: &[&str]) -> String {
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(dir.join("covid.json"), format!("[{},\n{}]", CASE, CASE)).unwrap();
+    std::fs::write(dir.join("census.json"), CENSUS).unwrap();
+    std::fs::write(dir.join("neighbourhoods.geojson"), NEIGHBOURHOODS).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_toronto-covid"))
+        .current_dir(dir)
+        .args([
+            "--covid=covid.json",
+            "--census=census.json",
+            "--neighbourhoods=neighbourhoods.geojson",
+            "--format=csv",
+            "--out=out.csv",
+            "--fsa-out=fsa.json",
+            "--crosswalk-out=crosswalk.json",
+            "--no-cache",
+            "--quiet",
+        ])
+        .args(extra)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let csv = std::fs::read_to_string(dir.join("out.csv")).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    csv.lines()
+        .find(|line| line.starts_with("Wychwood,"))
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn dedup_counts_a_repeated_id_once() {
+    let dir = std::env::temp_dir().join(format!("dedup-{}", std::process::id()));
+    assert_eq!(
+        wychwood_row(&dir, &["--dedup"]),
+        "Wychwood,14349,1,6.969126768415918"
+    );
+}
+
+#[test]
+fn repeated_ids_count_twice_without_dedup() {
+    let dir = std::env::temp_dir().join(format!("no-dedup-{}", std::process::id()));
+    assert_eq!(
+        wychwood_row(&dir, &[]),
+        "Wychwood,14349,2,13.938253536831835"
+    );
+}
@@ -0,0 +1,128 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use toronto_covid::fetch::{fetch, FetchError, Retry, COVID_CASES, NEIGHBOURHOODS};
+
+/// Serves CKAN packages on a local port: `NEIGHBOURHOODS` as a file and `COVID_CASES` through the
+/// datastore, answering the first `failures` requests with a 503. Returns the base URL and the
+/// count of requests served.
+fn mock_ckan(failures: usize) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served = requests.clone();
+    let file_url = format!("{}/neighbourhoods.geojson", base_url);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            reader.read_line(&mut request_line).unwrap();
+            // the rest of the headers
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            let (status, body) = if served.fetch_add(1, Ordering::SeqCst) < failures {
+                ("503 Service Unavailable", "try again later".to_owned())
+            } else if request_line.contains("package_show?id=covid-19-cases-in-toronto") {
+                let package = serde_json::json!({
+                    "success": true,
+                    "result": { "resources": [
+                        { "id": "2", "name": "COVID19 cases", "url": "", "datastore_active": true },
+                    ] },
+                });
+                ("200 OK", package.to_string())
+            } else if request_line.contains("/datastore/dump/2?format=json") {
+                let dump = serde_json::json!({
+                    "fields": [{ "id": "_id", "type": "int" }, { "id": "FSA", "type": "text" }],
+                    "records": [[1, "M5V"], [2, "M6C"]],
+                });
+                ("200 OK", dump.to_string())
+            } else if request_line.contains("package_show") {
+                let package = serde_json::json!({
+                    "success": true,
+                    "result": { "resources": [
+                        { "id": "1", "name": "Neighbourhoods", "url": file_url },
+                    ] },
+                });
+                ("200 OK", package.to_string())
+            } else {
+                (
+                    "200 OK",
+                    r#"{"type":"FeatureCollection","features":[]}"#.to_owned(),
+                )
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    (base_url, requests)
+}
+
+fn retry(attempts: u32) -> Retry {
+    Retry {
+        attempts,
+        backoff: Duration::from_millis(10),
+        timeout: Duration::from_secs(5),
+    }
+}
+
+#[test]
+fn retries_until_the_server_recovers() {
+    let (base_url, requests) = mock_ckan(2);
+    let dest = std::env::temp_dir().join(format!("fetch-retry-{}.geojson", std::process::id()));
+    fetch(&base_url, &NEIGHBOURHOODS, &dest, &retry(3)).unwrap();
+
+    // two failures, then the package and the file
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+    let body = std::fs::read_to_string(&dest).unwrap();
+    std::fs::remove_file(&dest).unwrap();
+    assert!(body.contains("FeatureCollection"));
+}
+
+#[test]
+fn gives_up_after_the_last_attempt() {
+    let (base_url, requests) = mock_ckan(5);
+    let dest = std::env::temp_dir().join(format!("fetch-give-up-{}.geojson", std::process::id()));
+    let e = fetch(&base_url, &NEIGHBOURHOODS, &dest, &retry(2)).unwrap_err();
+
+    assert!(matches!(e, FetchError::Http { attempts: 2, .. }));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    assert!(!dest.exists());
+}
+
+#[test]
+fn reads_file_and_datastore_resources() {
+    let (base_url, _) = mock_ckan(0);
+    let dir = std::env::temp_dir();
+    let geojson = dir.join(format!("fetch-file-{}.geojson", std::process::id()));
+    let cases = dir.join(format!("fetch-datastore-{}.json", std::process::id()));
+    std::fs::write(&geojson, "an older copy").unwrap();
+    fetch(&base_url, &NEIGHBOURHOODS, &geojson, &retry(1)).unwrap();
+    fetch(&base_url, &COVID_CASES, &cases, &retry(1)).unwrap();
+
+    let body = std::fs::read_to_string(&geojson).unwrap();
+    std::fs::remove_file(&geojson).unwrap();
+    assert_eq!(body, r#"{"type":"FeatureCollection","features":[]}"#);
+
+    let body = std::fs::read_to_string(&cases).unwrap();
+    std::fs::remove_file(&cases).unwrap();
+    let rows: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        rows,
+        serde_json::json!([{ "_id": 1, "FSA": "M5V" }, { "_id": 2, "FSA": "M6C" }])
+    );
+    let mut tmp = cases.into_os_string();
+    tmp.push(".tmp");
+    assert!(!std::path::Path::new(&tmp).exists());
+}
@@ -0,0 +1,63 @@
+use serde_json::json;
+use toronto_covid::filter::{Filter, FilterError};
+use toronto_covid::CovidEntry;
+
+fn case(age_group: &str, outbreak: &str, classification: &str) -> CovidEntry {
+    serde_json::from_value(json!({
+        "_id": 1,
+        "Outbreak Associated": outbreak,
+        "Age Group": age_group,
+        "Neighbourhood Name": "Wychwood",
+        "FSA": "M6C",
+        "Client Gender": "FEMALE",
+        "Outcome": "RESOLVED",
+        "Episode Date": "2020-04-01",
+        "Reported Date": "2020-04-03",
+        "Classification": classification,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn age_groups_compare_in_order() {
+    let filter: Filter = "age_group>=60".parse().unwrap();
+    assert!(filter.matches(&case("60-69", "Sporadic", "CONFIRMED")));
+    assert!(filter.matches(&case("90+", "Sporadic", "CONFIRMED")));
+    assert!(!filter.matches(&case("50-59", "Sporadic", "CONFIRMED")));
+    assert!(!filter.matches(&case("", "Sporadic", "CONFIRMED")));
+}
+
+#[test]
+fn conjunction_needs_every_comparison() {
+    let filter: Filter = "age_group >= 60 && outbreak_associated == 'Sporadic'"
+        .parse()
+        .unwrap();
+    assert!(filter.matches(&case("70-79", "Sporadic", "CONFIRMED")));
+    assert!(!filter.matches(&case("70-79", "Outbreak Associated", "CONFIRMED")));
+    assert!(!filter.matches(&case("20-29", "Sporadic", "CONFIRMED")));
+
+    let filter: Filter = "classification==probable || age_group<20".parse().unwrap();
+    assert!(filter.matches(&case("50-59", "Sporadic", "PROBABLE")));
+    assert!(filter.matches(&case("19 and younger", "Sporadic", "CONFIRMED")));
+    assert!(!filter.matches(&case("50-59", "Sporadic", "CONFIRMED")));
+}
+
+#[test]
+fn rejects_unknown_fields_and_malformed_expressions() {
+    assert!(matches!(
+        "postal_code==M6C".parse::<Filter>(),
+        Err(FilterError::UnknownField(field)) if field == "postal_code"
+    ));
+    assert!(matches!(
+        "age_group 60".parse::<Filter>(),
+        Err(FilterError::Syntax(_))
+    ));
+    assert!(matches!(
+        "outcome>FATAL".parse::<Filter>(),
+        Err(FilterError::Unordered { op: ">", .. })
+    ));
+    assert!(matches!(
+        "classification==maybe".parse::<Filter>(),
+        Err(FilterError::InvalidValue { .. })
+    ));
+}
@@ -0,0 +1,46 @@
+use toronto_covid::{best_match, FUZZY_THRESHOLD, NEIGHBOURHOOD_NAMES};
+
+const CANDIDATES: [&str; 3] = ["Annex", "Wychwood", "Casa Loma"];
+
+#[test]
+fn threshold_zero_only_matches_exactly() {
+    assert_eq!(
+        best_match("Wychwood", &CANDIDATES, 0),
+        Some(("Wychwood", 0))
+    );
+    assert_eq!(best_match("Wychwod", &CANDIDATES, 0), None);
+    assert_eq!(best_match("Wichwod", &CANDIDATES, 0), None);
+}
+
+#[test]
+fn threshold_one_matches_a_single_edit() {
+    assert_eq!(best_match("Wychwod", &CANDIDATES, 1), Some(("Wychwood", 1)));
+    assert_eq!(best_match("Wichwod", &CANDIDATES, 1), None);
+}
+
+#[test]
+fn threshold_two_matches_two_edits() {
+    assert_eq!(best_match("Wychwod", &CANDIDATES, 2), Some(("Wychwood", 1)));
+    assert_eq!(best_match("Wichwod", &CANDIDATES, 2), Some(("Wychwood", 2)));
+    assert_eq!(best_match("Wichwd", &CANDIDATES, 2), None);
+}
+
+#[test]
+fn near_miss_matches_a_known_neighbourhood() {
+    assert_eq!(
+        best_match("Wychwodd", &NEIGHBOURHOOD_NAMES, FUZZY_THRESHOLD),
+        Some(("Wychwood", 1))
+    );
+    assert_eq!(
+        best_match("Weston Pelham Park", &NEIGHBOURHOOD_NAMES, FUZZY_THRESHOLD),
+        Some(("Weston-Pelham Park", 1))
+    );
+}
+
+#[test]
+fn distant_name_matches_nothing() {
+    assert_eq!(
+        best_match("Atlantis", &NEIGHBOURHOOD_NAMES, FUZZY_THRESHOLD),
+        None
+    );
+}
@@ -0,0 +1,103 @@
+use serde_json::json;
+use toronto_covid::geometry::{area_km2, centroid, contains, locate};
+
+fn value(geometry: serde_json::Value) -> geojson::Value {
+    let geometry: geojson::Geometry = serde_json::from_value(geometry).unwrap();
+    geometry.value
+}
+
+fn square(x: f64, y: f64, side: f64) -> serde_json::Value {
+    json!([[
+        [x, y],
+        [x + side, y],
+        [x + side, y + side],
+        [x, y + side],
+        [x, y]
+    ]])
+}
+
+#[test]
+fn unit_square_area_in_km2() {
+    // one degree by one degree at the equator on the WGS84 sphere: R² · Δλ · (sin 1° − sin 0°)
+    let area = area_km2(&value(json!({
+        "type": "Polygon",
+        "coordinates": square(0.0, 0.0, 1.0),
+    })))
+    .unwrap();
+    assert!((area - 12_391.4).abs() < 0.1, "{}", area);
+}
+
+#[test]
+fn multipolygon_area_sums_its_parts() {
+    let one = area_km2(&value(json!({
+        "type": "Polygon",
+        "coordinates": square(0.0, 0.0, 1.0),
+    })))
+    .unwrap();
+    let both = area_km2(&value(json!({
+        "type": "MultiPolygon",
+        "coordinates": [square(0.0, 0.0, 1.0), square(5.0, 0.0, 1.0)],
+    })))
+    .unwrap();
+    assert!((both - 2.0 * one).abs() < 1e-6, "{} vs {}", both, one);
+    assert_eq!(
+        area_km2(&value(
+            json!({ "type": "Point", "coordinates": [0.0, 0.0] })
+        )),
+        None
+    );
+}
+
+#[test]
+fn square_centroid_is_its_middle() {
+    let middle = centroid(&value(json!({
+        "type": "Polygon",
+        "coordinates": square(-79.42, 43.6, 0.02),
+    })))
+    .unwrap();
+    assert!(
+        (middle.0 - -79.41).abs() < 1e-9 && (middle.1 - 43.61).abs() < 1e-9,
+        "{:?}",
+        middle
+    );
+}
+
+#[test]
+fn multipolygon_centroid_is_that_of_the_largest_part() {
+    let middle = centroid(&value(json!({
+        "type": "MultiPolygon",
+        "coordinates": [square(0.0, 0.0, 1.0), square(10.0, 10.0, 4.0)],
+    })))
+    .unwrap();
+    assert_eq!(middle, (12.0, 12.0));
+}
+
+#[test]
+fn locates_a_point_in_the_feature_containing_it() {
+    let neighbourhoods: geojson::FeatureCollection = serde_json::from_value(json!({
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": { "AREA_NAME": "Annex (95)" },
+                "geometry": { "type": "Polygon", "coordinates": square(-79.41, 43.66, 0.02) },
+            },
+            {
+                "type": "Feature",
+                "properties": { "AREA_NAME": "Wychwood (94)" },
+                "geometry": { "type": "Polygon", "coordinates": square(-79.43, 43.67, 0.01) },
+            },
+        ],
+    }))
+    .unwrap();
+
+    let wychwood = locate(&neighbourhoods, -79.425, 43.675).unwrap();
+    assert_eq!(
+        wychwood.properties.as_ref().unwrap()["AREA_NAME"],
+        "Wychwood (94)"
+    );
+    assert!(locate(&neighbourhoods, -80.0, 43.675).is_none());
+    let geometry = &wychwood.geometry.as_ref().unwrap().value;
+    assert!(contains(geometry, -79.425, 43.675));
+    assert!(!contains(geometry, -79.40, 43.675));
+}
@@ -0,0 +1,49 @@
+use serde_json::json;
+use toronto_covid::output::write_long_csv;
+
+#[test]
+fn matches_golden_file() {
+    let collection = serde_json::from_value(json!({
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": {
+                    "AREA_NAME": "Wychwood (94)",
+                    "name": "Wychwood",
+                    "population": 14349,
+                    "population_density": 9163.5,
+                    "covid_case_count": 3,
+                    "cases_per_100k": 20.9,
+                    "covid_rate": 20.9,
+                    "covid_outbreak_count": 2,
+                    "covid_outbreak_share": 0.5,
+                    "covid_active": 0,
+                    "covid_resolved": 3,
+                    "covid_fatal": 0,
+                    "covid_cfr": 0.0,
+                },
+                "geometry": null,
+            },
+            {
+                "type": "Feature",
+                "properties": {
+                    "AREA_NAME": "Annex (95)",
+                    "name": "Annex",
+                    "population": 30526,
+                    "population_density": 10863.0,
+                    "covid_case_count": null,
+                    "cases_per_100k": null,
+                    "suppressed": true,
+                },
+                "geometry": null,
+            },
+        ],
+    }))
+    .unwrap();
+
+    let mut csv = Vec::new();
+    write_long_csv(&mut csv, &collection).unwrap();
+    let expected = std::fs::read_to_string("tests/fixtures/long.csv").unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), expected);
+}
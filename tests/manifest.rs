@@ -0,0 +1,25 @@
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use toronto_covid::stream::{open_hashed, JsonArray};
+use toronto_covid::CovidEntry;
+
+#[test]
+fn records_rows_size_and_hash_of_an_input() {
+    let path = Path::new("tests/fixtures/covid.json");
+    let (file, fingerprint) = open_hashed(path).unwrap();
+    let rows = JsonArray::<_, CovidEntry>::new(file)
+        .map(Result::unwrap)
+        .count();
+    // the parser stops at the closing bracket, before the trailing newline
+    let record = fingerprint.finish(rows as u64).unwrap();
+
+    let contents = std::fs::read(path).unwrap();
+    let expected = Sha256::digest(&contents)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    assert_eq!(record.path, path);
+    assert_eq!(record.records, 3);
+    assert_eq!(record.bytes, contents.len() as u64);
+    assert_eq!(record.sha256, expected);
+}
@@ -0,0 +1,27 @@
+use toronto_covid::{normalize_for_match, Names};
+
+#[test]
+fn alias_round_trips_through_its_canonical_name() {
+    let names = Names::default();
+    let canonical = names.canonicalize("Weston-Pellam Park");
+    assert_eq!(canonical, "Weston-Pelham Park");
+    assert!(names
+        .aliases_of(canonical)
+        .iter()
+        .any(|a| a == "Weston-Pellam Park"));
+    assert_eq!(names.canonicalize(canonical), canonical);
+}
+
+#[test]
+fn matches_regardless_of_case_and_whitespace() {
+    assert_eq!(
+        normalize_for_match(" THE  Beaches "),
+        normalize_for_match("The Beaches")
+    );
+    assert_eq!(
+        normalize_for_match("North St.James Town"),
+        normalize_for_match("North St. James Town")
+    );
+    let names = Names::default();
+    assert_eq!(names.canonicalize(" THE  Beaches "), "The Beaches");
+}
@@ -0,0 +1,15 @@
+use toronto_covid::OutbreakAssociated;
+
+#[test]
+fn labels_round_trip() {
+    for (label, expected) in [
+        ("Outbreak Associated", OutbreakAssociated::Outbreak),
+        ("Sporadic", OutbreakAssociated::Sporadic),
+        ("Travel", OutbreakAssociated::Unknown("Travel".to_owned())),
+    ] {
+        let json = serde_json::to_string(label).unwrap();
+        let parsed: OutbreakAssociated = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, expected);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+}
@@ -0,0 +1,54 @@
+use toronto_covid::{build_output, parse_census_csv, populations, CensusError, Names};
+
+const CENSUS: &str = "\
+_id,Category,Topic,Data Source,Characteristic,City of Toronto,Wychwood,Annex,Casa Loma
+1,Neighbourhood Information,Neighbourhood Information,City of Toronto,Neighbourhood Number,,94,95,96
+3,Population,Population and dwellings,Census Profile 98-316-X2016001,\"Population, 2016\",\"2,731,571\",\"14,349\",\"30,526\",\"10,968\"
+4,Population,Population and dwellings,Census Profile 98-316-X2016001,\"Population, 2011\",\"2,615,060\",\"14,207\",\"29,177\",\"10,444\"
+";
+
+#[test]
+fn reads_the_chosen_population_row() {
+    let census = parse_census_csv(CENSUS.as_bytes(), "Population, 2011", |_| false).unwrap();
+    assert_eq!(census.len(), 2);
+    assert_eq!(census[1].entry().characteristic, "Population, 2011");
+
+    let read = populations(census, &Names::default(), " Population, 2011 ").unwrap();
+    assert_eq!(read["City of Toronto"], 2615060);
+    assert_eq!(read["Wychwood"], 14207);
+    assert_eq!(read["Casa Loma"], 10444);
+}
+
+#[test]
+fn missing_population_row_names_the_field() {
+    let census = parse_census_csv(CENSUS.as_bytes(), "Population, 2021", |_| true).unwrap();
+    match populations(census, &Names::default(), "Population, 2021") {
+        Err(e @ CensusError::MissingPopulation { .. }) => assert_eq!(
+            e.to_string(),
+            "none of the 0 \"Population, 2021\" row(s) in the census has category \"Population\" \
+             and topic \"Population and dwellings\""
+        ),
+        other => panic!(
+            "expected a missing population, got {:?}",
+            other.map(|p| p.len())
+        ),
+    }
+}
+
+#[test]
+fn builds_output_from_the_chosen_population_row() {
+    let census = parse_census_csv(CENSUS.as_bytes(), "Population, 2011", |_| false).unwrap();
+    let geojson = serde_json::from_value(serde_json::json!({
+        "type": "FeatureCollection",
+        "features": [{
+            "type": "Feature",
+            "properties": { "AREA_NAME": "Wychwood (94)" },
+            "geometry": null,
+        }],
+    }))
+    .unwrap();
+    let output =
+        build_output(&[], &census, geojson, "Population, 2011", &Names::default()).unwrap();
+    let properties = output.features[0].properties.as_ref().unwrap();
+    assert_eq!(properties["population"], 14207);
+}
@@ -0,0 +1,42 @@
+use geojson::FeatureCollection;
+use serde_json::{json, Value};
+use toronto_covid::attach_ranks;
+
+fn collection(rates: &[Option<f64>]) -> FeatureCollection {
+    let features = rates
+        .iter()
+        .enumerate()
+        .map(|(i, rate)| {
+            json!({
+                "type": "Feature",
+                "properties": { "AREA_NAME": format!("Neighbourhood {}", i), "cases_per_100k": rate },
+                "geometry": null,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::from_value(json!({ "type": "FeatureCollection", "features": features })).unwrap()
+}
+
+#[test]
+fn ranks_by_rate_with_shared_ties() {
+    let mut neighbourhoods = collection(&[Some(50.0), Some(200.0), None, Some(50.0), Some(10.0)]);
+    attach_ranks(&mut neighbourhoods);
+    let ranked = neighbourhoods
+        .features
+        .iter()
+        .map(|f| {
+            let p = f.properties.as_ref().unwrap();
+            (p["rate_rank"].clone(), p["rate_percentile"].clone())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranked,
+        [
+            (json!(2), json!(1.0 / 3.0 * 100.0)),
+            (json!(1), json!(100.0)),
+            (Value::Null, Value::Null),
+            (json!(2), json!(1.0 / 3.0 * 100.0)),
+            (json!(4), json!(0.0)),
+        ]
+    );
+}
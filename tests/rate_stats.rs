@@ -0,0 +1,44 @@
+use geojson::FeatureCollection;
+use serde_json::json;
+use toronto_covid::output::{feature_rates, RateStats};
+
+/// Features with the given `cases_per_100k`, `None` standing for a neighbourhood without a
+/// population.
+fn features(rates: &[Option<f64>]) -> FeatureCollection {
+    let features = rates
+        .iter()
+        .map(|rate| {
+            json!({
+                "type": "Feature",
+                "properties": { "cases_per_100k": rate },
+                "geometry": null,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::from_value(json!({ "type": "FeatureCollection", "features": features })).unwrap()
+}
+
+#[test]
+fn stats_leave_out_neighbourhoods_without_a_population() {
+    let neighbourhoods = features(&[Some(40.0), None, Some(10.0), Some(30.0), None, Some(20.0)]);
+    let rates = feature_rates(&neighbourhoods);
+    assert_eq!(rates, [40.0, 10.0, 30.0, 20.0]);
+
+    let stats = RateStats::new(rates).unwrap();
+    assert_eq!(
+        stats,
+        RateStats {
+            min: 10.0,
+            max: 40.0,
+            mean: 25.0,
+            median: 25.0,
+        }
+    );
+}
+
+#[test]
+fn median_of_an_odd_number_of_rates_is_the_middle_one() {
+    let stats = RateStats::new(vec![5.0, 100.0, 7.0]).unwrap();
+    assert_eq!(stats.median, 7.0);
+    assert_eq!(RateStats::new(Vec::new()), None);
+}
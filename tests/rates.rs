@@ -0,0 +1,57 @@
+use toronto_covid::{
+    case_fatality_rate, cumulative_incidence_pct, outbreak_share, rate_per, rate_per_100k,
+};
+
+#[test]
+fn rate_per_100k_scales_to_the_population() {
+    assert_eq!(rate_per_100k(50, 20_000), Some(250.0));
+    assert_eq!(rate_per_100k(0, 20_000), Some(0.0));
+}
+
+#[test]
+fn rate_per_100k_without_population_is_none() {
+    assert_eq!(rate_per_100k(5, 0), None);
+}
+
+#[test]
+fn rate_per_scales_to_the_denominator() {
+    assert_eq!(rate_per(50, 20_000, 1_000), Some(2.5));
+    assert_eq!(rate_per(50, 20_000, 100_000), Some(250.0));
+    assert_eq!(rate_per(50, 0, 1_000), None);
+}
+
+#[test]
+fn case_fatality_rate_is_fatal_over_closed_cases() {
+    assert_eq!(case_fatality_rate(1, 3), Some(0.25));
+    assert_eq!(case_fatality_rate(0, 4), Some(0.0));
+    assert_eq!(case_fatality_rate(2, 0), Some(1.0));
+}
+
+#[test]
+fn case_fatality_rate_without_closed_cases_is_none() {
+    assert_eq!(case_fatality_rate(0, 0), None);
+}
+
+#[test]
+fn outbreak_share_is_outbreak_over_total() {
+    assert_eq!(outbreak_share(1, 4), Some(0.25));
+    assert_eq!(outbreak_share(0, 4), Some(0.0));
+    assert_eq!(outbreak_share(3, 3), Some(1.0));
+}
+
+#[test]
+fn outbreak_share_without_cases_is_none() {
+    assert_eq!(outbreak_share(0, 0), None);
+}
+
+#[test]
+fn cumulative_incidence_is_a_percentage_of_the_population() {
+    assert_eq!(cumulative_incidence_pct(150, 20_000), Some(0.75));
+    assert_eq!(cumulative_incidence_pct(0, 20_000), Some(0.0));
+}
+
+#[test]
+fn cumulative_incidence_is_capped_and_needs_a_population() {
+    assert_eq!(cumulative_incidence_pct(300, 200), Some(100.0));
+    assert_eq!(cumulative_incidence_pct(5, 0), None);
+}
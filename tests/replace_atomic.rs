@@ -0,0 +1,19 @@
+use toronto_covid::output::replace_atomic;
+
+#[test]
+fn replaces_the_file_once_written() {
+    let dir = std::env::temp_dir().join(format!("replace-atomic-{}", std::process::id()));
+    let path = dir.join("nested").join("out.geojson");
+    replace_atomic(&path, |tmp| std::fs::write(tmp, "first")).unwrap();
+    replace_atomic(&path, |tmp| std::fs::write(tmp, "second")).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+
+    let failed = replace_atomic(&path, |tmp| {
+        std::fs::write(tmp, "trunc")?;
+        Err(std::io::Error::other("interrupted"))
+    });
+    assert!(failed.is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+    assert!(!dir.join("nested").join("out.geojson.tmp").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
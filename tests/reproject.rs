@@ -0,0 +1,45 @@
+use serde_json::json;
+use toronto_covid::geometry::web_mercator;
+use toronto_covid::reproject_web_mercator;
+
+fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+    (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01
+}
+
+#[test]
+fn projects_known_positions() {
+    assert!(close(web_mercator(0.0, 0.0), (0.0, 0.0)));
+    assert!(close(web_mercator(180.0, 0.0), (20_037_508.34, 0.0)));
+    // Toronto City Hall
+    assert!(close(
+        web_mercator(-79.3832, 43.6532),
+        (-8_836_897.40, 5_411_930.00)
+    ));
+}
+
+#[test]
+fn reprojects_features_and_names_the_crs() {
+    let mut collection = serde_json::from_value(json!({
+        "type": "FeatureCollection",
+        "features": [{
+            "type": "Feature",
+            "properties": { "AREA_NAME": "Somewhere" },
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]],
+            },
+        }],
+    }))
+    .unwrap();
+    reproject_web_mercator(&mut collection);
+
+    let output = serde_json::to_value(&collection).unwrap();
+    assert_eq!(
+        output["crs"]["properties"]["name"],
+        "urn:ogc:def:crs:EPSG::3857"
+    );
+    let corner = &output["features"][0]["geometry"]["coordinates"][0][2];
+    let (x, y) = (corner[0].as_f64().unwrap(), corner[1].as_f64().unwrap());
+    assert!(close((x, y), web_mercator(1.0, 1.0)));
+    assert!(close((x, y), (111_319.49, 111_325.14)));
+}
@@ -0,0 +1,83 @@
+use geojson::{Feature, FeatureCollection, Geometry};
+use toronto_covid::{geometry, simplify_geometries};
+
+fn polygon(ring: &[[f64; 2]]) -> geojson::Value {
+    geojson::Value::Polygon(vec![ring.iter().map(|p| p.to_vec()).collect()])
+}
+
+fn exterior(value: &geojson::Value) -> Vec<Vec<f64>> {
+    match value {
+        geojson::Value::Polygon(rings) => rings[0].clone(),
+        other => panic!("expected a polygon, got {:?}", other),
+    }
+}
+
+#[test]
+fn drops_near_collinear_vertex() {
+    // (1, 0.0001) sits almost on the bottom edge
+    let square = polygon(&[
+        [0.0, 0.0],
+        [1.0, 0.0001],
+        [2.0, 0.0],
+        [2.0, 2.0],
+        [0.0, 2.0],
+        [0.0, 0.0],
+    ]);
+    let simplified = geometry::simplify(&square, 0.001).unwrap();
+    assert_eq!(
+        exterior(&simplified),
+        vec![
+            vec![0.0, 0.0],
+            vec![2.0, 0.0],
+            vec![2.0, 2.0],
+            vec![0.0, 2.0],
+            vec![0.0, 0.0],
+        ]
+    );
+}
+
+#[test]
+fn keeps_rings_that_would_collapse() {
+    let triangle = polygon(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [0.0, 0.0]]);
+    let simplified = geometry::simplify(&triangle, 10.0).unwrap();
+    assert_eq!(exterior(&simplified), exterior(&triangle));
+}
+
+#[test]
+fn leaves_properties_alone() {
+    let mut properties = serde_json::Map::new();
+    properties.insert("name".to_owned(), "Wychwood".into());
+    properties.insert("covid_case_count".to_owned(), 3.into());
+    let feature = |value| Feature {
+        bbox: None,
+        geometry: Some(Geometry::new(value)),
+        id: None,
+        properties: Some(properties.clone()),
+        foreign_members: None,
+    };
+    let mut collection = FeatureCollection {
+        bbox: None,
+        features: vec![
+            feature(polygon(&[
+                [0.0, 0.0],
+                [1.0, 0.0001],
+                [2.0, 0.0],
+                [2.0, 2.0],
+                [0.0, 0.0],
+            ])),
+            feature(geojson::Value::Point(vec![1.0, 1.0])),
+        ],
+        foreign_members: None,
+    };
+    simplify_geometries(&mut collection, 0.001);
+
+    let simplified = collection.features[0].geometry.as_ref().unwrap();
+    assert_eq!(exterior(&simplified.value).len(), 4);
+    assert_eq!(
+        collection.features[1].geometry.as_ref().unwrap().value,
+        geojson::Value::Point(vec![1.0, 1.0])
+    );
+    for feature in collection.features.iter() {
+        assert_eq!(feature.properties.as_ref(), Some(&properties));
+    }
+}
@@ -0,0 +1,49 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::Write;
+use std::path::Path;
+use toronto_covid::stream::{is_json_lines, open, JsonArray, JsonLines};
+use toronto_covid::CovidEntry;
+
+const CASE: &str = r#"[{"_id": 1, "Outbreak Associated": "Sporadic", "Age Group": "50-59", "Neighbourhood Name": "Wychwood", "FSA": "M6C", "Client Gender": "FEMALE", "Outcome": "RESOLVED", "Episode Date": "2020-04-01", "Reported Date": "2020-04-03", "Classification": "CONFIRMED"}]"#;
+
+fn read_cases(path: &Path) -> Vec<CovidEntry> {
+    JsonArray::<_, CovidEntry>::new(open(path).unwrap())
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn reads_a_gzipped_input() {
+    let dir = std::env::temp_dir();
+    let plain = dir.join(format!("stream-plain-{}.json", std::process::id()));
+    let gzipped = dir.join(format!("stream-gzipped-{}.json.gz", std::process::id()));
+    std::fs::write(&plain, CASE).unwrap();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(CASE.as_bytes()).unwrap();
+    std::fs::write(&gzipped, encoder.finish().unwrap()).unwrap();
+
+    let from_plain = read_cases(&plain);
+    let from_gzipped = read_cases(&gzipped);
+    std::fs::remove_file(&plain).unwrap();
+    std::fs::remove_file(&gzipped).unwrap();
+    assert_eq!(from_gzipped.len(), 1);
+    assert_eq!(from_gzipped[0].id, 1);
+    assert_eq!(
+        serde_json::to_value(&from_gzipped).unwrap(),
+        serde_json::to_value(&from_plain).unwrap()
+    );
+}
+
+#[test]
+fn reads_json_lines_skipping_blank_ones() {
+    let path = Path::new("tests/fixtures/covid.ndjson");
+    assert!(is_json_lines(path));
+    assert!(is_json_lines(Path::new("cases.jsonl.gz")));
+    assert!(!is_json_lines(Path::new("tests/fixtures/covid.json")));
+
+    let ids = JsonLines::<_, CovidEntry>::new(open(path).unwrap())
+        .map(|e| e.unwrap().id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [1, 2, 3]);
+}
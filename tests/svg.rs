@@ -0,0 +1,37 @@
+use toronto_covid::svg::{self, Ramp};
+use toronto_covid::{build_output, parse_census, Names, DEFAULT_POPULATION_FIELD};
+
+#[test]
+fn draws_a_path_per_neighbourhood() {
+    let geojson = std::fs::read_to_string("Neighbourhoods.geojson")
+        .unwrap()
+        .parse()
+        .unwrap();
+    let census = std::fs::read_to_string("neighbourhood-profiles-2016-csv.json").unwrap();
+    let census = parse_census(&census, DEFAULT_POPULATION_FIELD, |_| false).unwrap();
+    let output = build_output(
+        &[],
+        &census,
+        geojson,
+        DEFAULT_POPULATION_FIELD,
+        &Names::default(),
+    )
+    .unwrap();
+
+    let mut image = Vec::new();
+    let ramp = "#ffffcc,#800026".parse::<Ramp>().unwrap();
+    svg::write(&mut image, &output, &ramp).unwrap();
+    let image = String::from_utf8(image).unwrap();
+    assert!(image.starts_with("<svg "));
+    assert_eq!(image.matches("<path ").count(), 140);
+}
+
+#[test]
+fn ramp_interpolates_between_colours() {
+    let ramp = "#000000,#ff8000,#ffffff".parse::<Ramp>().unwrap();
+    assert_eq!(ramp.colour(0.0), "#000000");
+    assert_eq!(ramp.colour(0.25), "#804000");
+    assert_eq!(ramp.colour(0.5), "#ff8000");
+    assert_eq!(ramp.colour(2.0), "#ffffff");
+    assert!("#fff".parse::<Ramp>().is_err());
+}
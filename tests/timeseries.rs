@@ -0,0 +1,41 @@
+use serde_json::json;
+use toronto_covid::output::timeseries;
+use toronto_covid::{aggregate, CovidEntry, Names};
+
+fn case(id: u32, neighbourhood: &str, episode_date: &str) -> CovidEntry {
+    serde_json::from_value(json!({
+        "_id": id,
+        "Outbreak Associated": "Sporadic",
+        "Age Group": "50-59",
+        "Neighbourhood Name": neighbourhood,
+        "Episode Date": episode_date,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn fills_in_months_without_cases() {
+    let aggregates = aggregate(
+        vec![
+            case(1, "Wychwood", "2020-04-12"),
+            case(2, "Annex", "2020-06-03"),
+        ],
+        &Names::default(),
+    );
+    let series = serde_json::to_value(timeseries(&aggregates.per_neighbourhood)).unwrap();
+    assert_eq!(
+        series,
+        json!({
+            "Annex": [
+                { "month": "2020-04", "count": 0 },
+                { "month": "2020-05", "count": 0 },
+                { "month": "2020-06", "count": 1 },
+            ],
+            "Wychwood": [
+                { "month": "2020-04", "count": 1 },
+                { "month": "2020-05", "count": 0 },
+                { "month": "2020-06", "count": 0 },
+            ],
+        })
+    );
+}
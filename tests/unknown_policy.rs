@@ -0,0 +1,100 @@
+use serde_json::{json, Value};
+use toronto_covid::{CaseCounts, CovidEntry, NeighbourhoodSummary, UnknownPolicy};
+
+fn case(id: u32, gender: Option<&str>, source: Option<&str>, outbreak: &str) -> CovidEntry {
+    serde_json::from_value(json!({
+        "_id": id,
+        "Outbreak Associated": outbreak,
+        "Age Group": "50-59",
+        "Neighbourhood Name": "Wychwood",
+        "FSA": "M6C",
+        "Client Gender": gender,
+        "Outcome": "RESOLVED",
+        "Episode Date": "2020-04-01",
+        "Reported Date": "2020-04-03",
+        "Classification": "CONFIRMED",
+        "Source of Infection": source,
+    }))
+    .unwrap()
+}
+
+/// Three known cases and one without a gender, source or outbreak association.
+fn summary(policy: UnknownPolicy) -> NeighbourhoodSummary {
+    let mut counts = CaseCounts::default();
+    counts.add(&case(
+        1,
+        Some("MALE"),
+        Some("Community"),
+        "Outbreak Associated",
+    ));
+    counts.add(&case(2, Some("FEMALE"), Some("Travel"), "Sporadic"));
+    counts.add(&case(3, Some("TRANSGENDER"), Some("Community"), "Sporadic"));
+    counts.add(&case(4, None, None, ""));
+    NeighbourhoodSummary::new(
+        "Wychwood".to_owned(),
+        &counts,
+        Some(10_000),
+        None,
+        None,
+        100_000,
+        policy,
+    )
+}
+
+fn source(summary: &NeighbourhoodSummary, name: &str) -> Option<u32> {
+    summary.sources.get(name).copied()
+}
+
+#[test]
+fn separate_counts_unknowns_on_their_own() {
+    let summary = summary(UnknownPolicy::Separate);
+    assert_eq!(summary.covid_case_count, 4);
+    assert_eq!(summary.covid_cases_other, 1);
+    assert_eq!(summary.covid_unknown_gender_count, Some(1));
+    assert_eq!(summary.covid_unknown_outbreak_count, Some(1));
+    assert_eq!(summary.covid_unknown_age_count, Some(0));
+    assert_eq!(summary.covid_outbreak_share, Some(0.25));
+    assert_eq!(source(&summary, "No Information"), Some(1));
+    assert_eq!(source(&summary, "Other"), None);
+}
+
+#[test]
+fn exclude_leaves_unknowns_out() {
+    let summary = summary(UnknownPolicy::Exclude);
+    assert_eq!(summary.covid_case_count, 4);
+    assert_eq!(summary.covid_cases_other, 1);
+    assert_eq!(summary.covid_unknown_gender_count, None);
+    assert_eq!(summary.covid_unknown_outbreak_count, None);
+    // the share of the three cases known to be outbreak associated or not
+    assert_eq!(summary.covid_outbreak_share, Some(1.0 / 3.0));
+    assert_eq!(source(&summary, "No Information"), None);
+    assert_eq!(source(&summary, "Other"), None);
+}
+
+#[test]
+fn include_as_other_folds_unknowns_into_other() {
+    let summary = summary(UnknownPolicy::IncludeAsOther);
+    assert_eq!(summary.covid_case_count, 4);
+    assert_eq!(summary.covid_cases_other, 2);
+    assert_eq!(summary.covid_unknown_gender_count, None);
+    assert_eq!(summary.covid_outbreak_share, Some(0.25));
+    assert_eq!(source(&summary, "No Information"), None);
+    assert_eq!(source(&summary, "Other"), Some(1));
+
+    let properties = serde_json::to_value(&summary).unwrap();
+    assert_eq!(properties["covid_unknown_gender_count"], Value::Null);
+}
+
+#[test]
+fn policies_parse_by_name() {
+    for policy in [
+        UnknownPolicy::Exclude,
+        UnknownPolicy::Separate,
+        UnknownPolicy::IncludeAsOther,
+    ]
+    .iter()
+    {
+        assert_eq!(policy.name().parse::<UnknownPolicy>(), Ok(*policy));
+    }
+    assert!("ignore".parse::<UnknownPolicy>().is_err());
+}
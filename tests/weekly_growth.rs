@@ -0,0 +1,36 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use toronto_covid::weekly_growth_pct;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2020, 4, day).unwrap()
+}
+
+fn days(counts: &[(u32, u32)]) -> BTreeMap<NaiveDate, u32> {
+    counts
+        .iter()
+        .map(|&(day, count)| (date(day), count))
+        .collect()
+}
+
+#[test]
+fn compares_last_week_to_the_one_before() {
+    // the last week is the 8th to the 14th, the one before the 1st to the 7th
+    let per_day = days(&[(1, 2), (7, 2), (8, 1), (14, 5)]);
+    assert_eq!(weekly_growth_pct(&per_day, date(1), date(14)), Some(50.0));
+
+    let per_day = days(&[(3, 4), (10, 1)]);
+    assert_eq!(weekly_growth_pct(&per_day, date(1), date(14)), Some(-75.0));
+}
+
+#[test]
+fn zero_prior_week_has_no_growth() {
+    let per_day = days(&[(10, 3)]);
+    assert_eq!(weekly_growth_pct(&per_day, date(1), date(14)), None);
+}
+
+#[test]
+fn short_history_has_no_growth() {
+    let per_day = days(&[(2, 1), (14, 1)]);
+    assert_eq!(weekly_growth_pct(&per_day, date(2), date(14)), None);
+}